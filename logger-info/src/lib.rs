@@ -3,6 +3,18 @@ use ascii_converter::string_to_decimals;
 use serde::{Serialize, Deserialize};
 use serde_yaml::{self};
 use std::net::Ipv4Addr;
+use trf_common::{Signature, VERBOSITY_VERDICTS, VERBOSITY_DETECTIONS, VERBOSITY_ALL};
+use trf_common::siem::Device;
+use rsyslogger::syslog::SyslogConfig;
+use rsyslogger::dropin::RsyslogConfig;
+use rsyslogger::file::FileConfig;
+use rsyslogger::metrics::MetricsConfig;
+use rsyslogger::sink::SinkConfig;
+
+pub mod signatures;
+use signatures::{SignatureRule, compile_signature};
+pub mod suricata;
+use suricata::load_rules;
 
 // --- RuleSet yml ---
 #[derive(Debug, Serialize, Deserialize)]
@@ -10,17 +22,89 @@ struct TrafficType {
     traffic_type: String,
     medium: String,
     block_type: String,
+    #[serde(default)]
+    id: Option<u32>,    // rule id reported with verdicts (defaults to the entry position, starting at 1)
+}
+
+// Event output format (`log_format`, next to log_type)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,   // "1.2.3.4 --> 5.6.7.8 - DROP - LOG: HTTP GET;"
+    Json,   // one JSON object per event (trf_common::event::Record)
+    Cef,    // ArcSight (trf_common::siem)
+    Leef,   // QRadar
+}
+
+// Packets reported as events (`verbosity`), the others are only counted (trf_common::VERBOSITY_*)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    Verdicts,       // drops, overrides, alerts and state changes
+    #[default]
+    Detections,     // + detections without a verdict
+    All,            // every packet (debugging)
+}
+
+impl Verbosity {
+    fn rule(self) -> u32 {
+        match self {
+            Verbosity::Verdicts => VERBOSITY_VERDICTS,
+            Verbosity::Detections => VERBOSITY_DETECTIONS,
+            Verbosity::All => VERBOSITY_ALL,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct RuleSet {
-    log_type: String,
+    #[serde(default)]
+    log_type: String,   // single output, used when `sinks` isn't set
+    #[serde(default)]
+    sinks: Vec<SinkConfig>,
+    #[serde(default)]
+    log_format: LogFormat,
+    #[serde(default)]
+    siem: Device,       // CEF / LEEF header
+    #[serde(default)]
+    syslog: SyslogConfig,
+    #[serde(default)]
+    rsyslog: RsyslogConfig,
+    #[serde(default)]
+    file: FileConfig,
+    #[serde(default)]
+    metrics: MetricsConfig,     // Prometheus /metrics (disabled unless `listen` is set)
     jndi_payload_header: String,
     whitelist: Vec<String>,
-    block: Vec<TrafficType>
+    block: Vec<TrafficType>,
+    #[serde(default)]
+    signatures: Vec<SignatureRule>,
+    #[serde(default)]
+    suricata_rules: Option<String>,
+    #[serde(default = "default_capture_snippets")]
+    capture_snippets: bool,     // payload snippets in events (false => privacy, nothing is copied)
+    #[serde(default)]
+    verbosity: Verbosity,
+}
+
+fn default_capture_snippets() -> bool {
+    true
 }
 // ---
 
+// Userspace config (the rule set itself is compiled into the eBPF program)
+#[derive(Debug)]
+pub struct LoggerConfig {
+    pub sinks: Vec<SinkConfig>,
+    pub log_format: LogFormat,
+    pub siem: Device,
+    pub rsyslog: RsyslogConfig,
+    pub metrics: MetricsConfig,
+    pub whitelist: Vec<u32>,
+    pub signatures: Vec<Signature>,
+}
+
 
 // HTTP Headers: default and custom headers from HTTP GET sent to SprinBoot
 trait DefHdrs {
@@ -43,25 +127,60 @@ impl DefHdrs for http::request::Builder {
     }
 }
 
+// RULE_SET index configured by a block entry
+fn rule_idx(traffic_type: &str, medium: &str) -> Option<usize> {
+    match (traffic_type, medium) {
+        ("Inbound", "JNDI") => Some(2),
+        ("Inbound", "JNDI:LDAP") => Some(3),
+        ("Inbound", "COMMONS_TEXT") => Some(8),
+        ("Inbound", "SPEL") => Some(9),
+        ("Outbound", "TCP") | ("Outbound", "HTTP") => Some(0),
+        ("Outbound", "LDAP") => Some(1),
+        ("Outbound", "LDAP:REQUEST") => Some(4),
+        ("Outbound", "RMI") => Some(5),
+        ("Outbound", "JAVA:OBJECT") => Some(6),
+        ("Outbound", "DNS") => Some(7),
+        _ => None,
+    }
+}
+
+// RULE_SET before the `block` rules: defaults, snippets (10) and verbosity (11)
+fn default_ruleset(rules: &RuleSet) -> [u8; 12usize] {
+    let mut ruleset = [0u8; 12usize];
+    ruleset[8] = 1; // Commons Text (alert)
+    ruleset[9] = 1; // SpEL (alert)
+    ruleset[10] = rules.capture_snippets as u8;
+    ruleset[11] = rules.verbosity.rule() as u8;
+    ruleset
+}
+
+// Unknown block_type of a `block` entry
+fn invalid_block_type(action: &TrafficType) -> String {
+    format!("invalid block_type for {} {}: {}", action.traffic_type, action.medium, action.block_type)
+}
+
 // Main configuation file: parses yaml to the necessary config files:
-// rule-set.dat, header-dec-seq.dat and header-offset.dat
-pub fn __config_logger_yml(file: &str) -> (String, Vec<u32>) {
-    let mut f = File::open(file).expect("Could not open file.");
-    let rules: RuleSet = serde_yaml::from_reader(f).expect("Could not read values.");
-    let mut ruleset = [0u8; 4usize];
-    let payloadkey = rules.jndi_payload_header;
-    f = File::create("trf-common/rule-set.dat").unwrap();
+// rule-set.dat, rule-ids.dat, header-dec-seq.dat and header-offset.dat
+// Invalid rule files (whitelist, block types, signatures, log_type) are returned
+// as errors before any of them is written.
+pub fn __config_logger_yml(file: &str) -> Result<LoggerConfig, String> {
+    let f = File::open(file).map_err(|e| format!("{}: {}", file, e))?;
+    let rules: RuleSet = serde_yaml::from_reader(f).map_err(|e| format!("{}: {}", file, e))?;
+    let mut ruleset = default_ruleset(&rules);
+    let payloadkey = &rules.jndi_payload_header;
 
     let mut whitelist: Vec<u32> = Vec::new();
     for host in &rules.whitelist {
-        let hspit: Vec<u8> = host.split(".").map(|h| h.parse::<u8>().unwrap()).collect();
-        if hspit.len() == 4 {
-            let addr: u32 = Ipv4Addr::new(hspit[0], hspit[1], hspit[2], hspit[3]).try_into().unwrap();
-            whitelist.push(addr);
-        }
+        let addr: Ipv4Addr = host.parse().map_err(|_| format!("invalid whitelist address: {}", host))?;
+        whitelist.push(u32::from(addr));
     }
 
-    for action in &rules.block {
+    // rule id of each RULE_SET entry (0 => default, not set in the rule file)
+    let mut ruleids = [0u32; 10usize];
+    for (pos, action) in rules.block.iter().enumerate() {
+        if let Some(idx) = rule_idx(&action.traffic_type, &action.medium) {
+            ruleids[idx] = action.id.unwrap_or(pos as u32 + 1);
+        }
         if action.traffic_type == "Inbound" {
             if action.medium == "JNDI" {
                 if action.block_type == "lookup" {
@@ -75,6 +194,15 @@ pub fn __config_logger_yml(file: &str) -> (String, Vec<u32>) {
                 } else if action.block_type == "request" {
                     ruleset[3] = 2;
                 }
+            } else if action.medium == "COMMONS_TEXT" || action.medium == "SPEL" {
+                let idx = if action.medium == "COMMONS_TEXT" { 8 } else { 9 };
+                if action.block_type == "disabled" {
+                    ruleset[idx] = 0;
+                } else if action.block_type == "alert" {
+                    ruleset[idx] = 1;
+                } else if action.block_type == "request" {
+                    ruleset[idx] = 2;
+                }
             }
         } else if action.traffic_type == "Outbound" {
             if action.medium == "TCP" {
@@ -83,14 +211,87 @@ pub fn __config_logger_yml(file: &str) -> (String, Vec<u32>) {
                 ruleset[0] = 2;
             } else if action.medium == "LDAP" {
                 ruleset[1] = 1;
+            } else if action.medium == "LDAP:REQUEST" {
+                if action.block_type == "alert" {
+                    ruleset[4] = 1;
+                } else if action.block_type == "drop" {
+                    ruleset[4] = 2;
+                } else if action.block_type == "allow" {
+                    ruleset[4] = 0;
+                } else {
+                    return Err(invalid_block_type(action));
+                }
+            } else if action.medium == "RMI" {
+                if action.block_type == "alert" {
+                    ruleset[5] = 1;
+                } else if action.block_type == "drop" {
+                    ruleset[5] = 2;
+                } else if action.block_type == "allow" {
+                    ruleset[5] = 0;
+                } else {
+                    return Err(invalid_block_type(action));
+                }
+            } else if action.medium == "JAVA:OBJECT" {
+                if action.block_type == "alert" {
+                    ruleset[6] = 1;
+                } else if action.block_type == "drop" {
+                    ruleset[6] = 2;
+                } else if action.block_type == "block" {
+                    ruleset[6] = 3;
+                } else if action.block_type == "allow" {
+                    ruleset[6] = 0;
+                } else {
+                    return Err(invalid_block_type(action));
+                }
+            } else if action.medium == "DNS" {
+                if action.block_type == "alert" {
+                    ruleset[7] = 1;
+                } else if action.block_type == "exfil" {
+                    ruleset[7] = 2;
+                } else if action.block_type == "lookup" {
+                    ruleset[7] = 3;
+                } else if action.block_type == "allow" {
+                    ruleset[7] = 0;
+                } else {
+                    return Err(invalid_block_type(action));
+                }
             }
         }
     }
-    
-    write!(f, "{:?}", ruleset).expect("write header offset to file");
-    __config_logger_payload(Some(&payloadkey));
 
-    return (rules.log_type, whitelist)
+    let mut signatures: Vec<Signature> = Vec::new();
+    for rule in &rules.signatures {
+        let sig = compile_signature(rule).map_err(|e| format!("invalid signature (id: {}): {}", rule.id, e))?;
+        signatures.push(sig);
+    }
+    if let Some(file) = &rules.suricata_rules {
+        signatures.extend(load_rules(file).into_iter().map(|rule| rule.signature));
+    }
+    if signatures.len() > trf_common::SIG_MAX as usize {
+        return Err(format!("too many signatures: {} (max {})", signatures.len(), trf_common::SIG_MAX));
+    }
+
+    let sinks = if rules.sinks.is_empty() {
+        SinkConfig::from_log_type(&rules.log_type, &rules.syslog, &rules.file, &rules.rsyslog)?
+    } else {
+        rules.sinks
+    };
+
+    let mut f = File::create("trf-common/rule-set.dat").map_err(|e| format!("rule-set.dat: {}", e))?;
+    write!(f, "{:?}", ruleset).map_err(|e| format!("rule-set.dat: {}", e))?;
+    f = File::create("trf-common/rule-ids.dat").map_err(|e| format!("rule-ids.dat: {}", e))?;
+    write!(f, "{:?}", ruleids).map_err(|e| format!("rule-ids.dat: {}", e))?;
+    __config_logger_payload(Some(payloadkey));
+
+    Ok(LoggerConfig {
+        sinks,
+        log_format: rules.log_format,
+        siem: rules.siem,
+        rsyslog: rules.rsyslog,
+        metrics: rules.metrics,
+        whitelist,
+        signatures,
+    })
 }
 
 /** Logger config files:
@@ -176,13 +377,52 @@ pub fn get_default_header_offset() -> (u8, u8) {
 #[cfg(test)]
 mod tests {
     use crate::{get_default_header_offset, get_template_header_offset, write_header, 
-        __config_logger_payload, __config_logger_yml};
+        __config_logger_payload, __config_logger_yml, default_ruleset, RuleSet, Verbosity};
+    use trf_common::{VERBOSITY_VERDICTS, VERBOSITY_DETECTIONS, VERBOSITY_ALL};
     use ascii_converter::string_to_decimals;
     use std::{fs::File, io::Write};
 
     #[test]
     fn boot_config_test() {
-        __config_logger_yml("src/draft-rule-set-default.yml")
+        __config_logger_yml("src/draft-rule-set-default.yml").unwrap();
+    }
+
+    #[test]
+    fn config_errors_test() {
+        let config = |name: &str, yml: &str| {
+            let path = std::env::temp_dir().join(format!("elogj-{}-{}.yml", std::process::id(), name));
+            File::create(&path).unwrap().write_all(yml.as_bytes()).unwrap();
+            let res = __config_logger_yml(path.to_str().unwrap()).unwrap_err();
+            std::fs::remove_file(&path).unwrap();
+            res
+        };
+        let rules = "jndi_payload_header: X-Api-Version\nlog_type: file\n";
+        assert!(__config_logger_yml("missing.yml").unwrap_err().starts_with("missing.yml: "));
+        assert!(config("yaml", "whitelist: [").contains("yaml.yml: "));
+        assert_eq!(config("whitelist", &format!("{}whitelist: [10.0.0.300]\nblock: []\n", rules)),
+            "invalid whitelist address: 10.0.0.300");
+        for medium in ["LDAP:REQUEST", "RMI", "JAVA:OBJECT", "DNS"] {
+            assert_eq!(config("block", &format!("{}whitelist: []\nblock:\n  - traffic_type: Outbound\n    medium: {}\n    \
+                block_type: dorp\n", rules, medium)), format!("invalid block_type for Outbound {}: dorp", medium));
+        }
+        assert!(config("log_type", "jndi_payload_header: X-Api-Version\nlog_type: remote\nwhitelist: []\nblock: []\n")
+            .starts_with("unknown log_type `remote`"));
+    }
+
+    #[test]
+    fn verbosity_test() {
+        let rules = |verbosity: &str| -> Result<RuleSet, serde_yaml::Error> {
+            serde_yaml::from_str(&format!("jndi_payload_header: X-Api-Version\nwhitelist: []\nblock: []\n{}", verbosity))
+        };
+        assert_eq!(rules("").unwrap().verbosity, Verbosity::Detections);
+        assert_eq!(default_ruleset(&rules("").unwrap())[11], VERBOSITY_DETECTIONS as u8);
+        for (name, verbosity, rule) in [("verdicts", Verbosity::Verdicts, VERBOSITY_VERDICTS),
+            ("detections", Verbosity::Detections, VERBOSITY_DETECTIONS), ("all", Verbosity::All, VERBOSITY_ALL)] {
+            let parsed = rules(&format!("verbosity: {}", name)).unwrap();
+            assert_eq!(parsed.verbosity, verbosity);
+            assert_eq!(default_ruleset(&parsed)[11], rule as u8);
+        }
+        assert!(rules("verbosity: debug").is_err());
     }
 
     // #[test]
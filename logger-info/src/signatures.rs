@@ -0,0 +1,201 @@
+use serde::{Serialize, Deserialize};
+use trf_common::{Signature, SIG_PATTERN_LEN, SIG_LOC_HEADER, SIG_LOC_URI, SIG_LOC_BODY, SIG_LOC_LDAP, SIG_LOC_PAYLOAD,
+    SIG_DIR_OUTBOUND, SIG_DIR_INBOUND, SIG_ALERT, SIG_DROP};
+
+// --- Signatures yml ---
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignatureRule {
+    pub id: u32,
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub nocase: bool,
+    pub location: String,
+    pub direction: String,
+    pub action: String,
+}
+// ---
+
+const NOCASE_MASK: u8 = 0xDF;
+
+/** Signature compiler:
+ * Both `pattern` and `regex` are compiled to a fixed size
+ * (pattern , mask) pair, matched byte by byte in kernelspace.
+ *
+ *  pattern --> literal bytes, `\xHH` for raw bytes and `\\` for a backslash.
+ *  regex   --> restricted regex: literals, `.` (any byte), `\xHH`, escaped
+ *              metacharacters (ex: `\$\{`) and a leading `(?i)` (nocase).
+ *              Quantifiers, alternations, groups, classes and anchors are rejected.
+**/
+pub fn compile_signature(rule: &SignatureRule) -> Result<Signature, String> {
+    let (bytes, nocase) = match (&rule.pattern, &rule.regex) {
+        (Some(pattern), None) => (parse_pattern(pattern)?, rule.nocase),
+        (None, Some(regex)) => {
+            let (bytes, nocase) = parse_regex(regex)?;
+            (bytes, nocase || rule.nocase)
+        },
+        (Some(_), Some(_)) => return Err(String::from("set either `pattern` or `regex`, not both")),
+        (None, None) => return Err(String::from("missing `pattern` or `regex`")),
+    };
+
+    build_signature(rule.id, &bytes, nocase, parse_location(&rule.location)?,
+        parse_direction(&rule.direction)?, parse_action(&rule.action)?)
+}
+
+// Pattern bytes (None => any byte) to Signature
+pub fn build_signature(id: u32, bytes: &[Option<u8>], nocase: bool, location: u32, direction: u32,
+    action: u32) -> Result<Signature, String> {
+    if bytes.is_empty() {
+        return Err(String::from("empty pattern"));
+    } else if bytes.len() > SIG_PATTERN_LEN {
+        return Err(format!("pattern longer than {} bytes", SIG_PATTERN_LEN));
+    }
+
+    let mut sig = Signature {
+        id,
+        len: bytes.len() as u32,
+        location,
+        direction,
+        action,
+        pattern: [0u8; SIG_PATTERN_LEN],
+        mask: [0u8; SIG_PATTERN_LEN],
+    };
+    for (i, byte) in bytes.iter().enumerate() {
+        match byte {
+            None => {}, // any byte (mask 0)
+            Some(b) if nocase && b.is_ascii_alphabetic() => {
+                sig.pattern[i] = b & NOCASE_MASK;
+                sig.mask[i] = NOCASE_MASK;
+            },
+            Some(b) => {
+                sig.pattern[i] = *b;
+                sig.mask[i] = 0xFF;
+            },
+        }
+    }
+    Ok(sig)
+}
+
+pub fn parse_location(location: &str) -> Result<u32, String> {
+    match location {
+        "header" => Ok(SIG_LOC_HEADER),
+        "uri" => Ok(SIG_LOC_URI),
+        "body" => Ok(SIG_LOC_BODY),
+        "ldap" => Ok(SIG_LOC_LDAP),
+        "payload" => Ok(SIG_LOC_PAYLOAD),
+        _ => Err(format!("unknown location `{}` (header | uri | body | ldap | payload)", location)),
+    }
+}
+
+pub fn parse_direction(direction: &str) -> Result<u32, String> {
+    match direction {
+        "Outbound" | "outbound" => Ok(SIG_DIR_OUTBOUND),
+        "Inbound" | "inbound" => Ok(SIG_DIR_INBOUND),
+        _ => Err(format!("unknown direction `{}` (Inbound | Outbound)", direction)),
+    }
+}
+
+pub fn parse_action(action: &str) -> Result<u32, String> {
+    match action {
+        "alert" => Ok(SIG_ALERT),
+        "drop" => Ok(SIG_DROP),
+        _ => Err(format!("unknown action `{}` (alert | drop)", action)),
+    }
+}
+
+// `\xHH` escape, chars holds the two hex digits
+fn parse_hex<I: Iterator<Item = char>>(chars: &mut I) -> Result<u8, String> {
+    let hex: String = chars.take(2).collect();
+    if hex.len() != 2 {
+        return Err(format!("incomplete escape `\\x{}`", hex));
+    }
+    u8::from_str_radix(&hex, 16).map_err(|_| format!("invalid escape `\\x{}`", hex))
+}
+
+fn parse_pattern(pattern: &str) -> Result<Vec<Option<u8>>, String> {
+    let mut bytes: Vec<Option<u8>> = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('x') => bytes.push(Some(parse_hex(&mut chars)?)),
+                Some('\\') => bytes.push(Some(b'\\')),
+                Some(e) => return Err(format!("unsupported escape `\\{}` in pattern", e)),
+                None => return Err(String::from("trailing `\\` in pattern")),
+            }
+        } else {
+            let mut buf = [0u8; 4];
+            bytes.extend(c.encode_utf8(&mut buf).bytes().map(Some));
+        }
+    }
+    Ok(bytes)
+}
+
+fn parse_regex(regex: &str) -> Result<(Vec<Option<u8>>, bool), String> {
+    let (regex, nocase) = match regex.strip_prefix("(?i)") {
+        Some(rest) => (rest, true),
+        None => (regex, false),
+    };
+    let mut bytes: Vec<Option<u8>> = Vec::new();
+    let mut chars = regex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('x') => bytes.push(Some(parse_hex(&mut chars)?)),
+                Some(e) if e.is_ascii_punctuation() => bytes.push(Some(e as u8)),
+                Some(e) => return Err(format!("unsupported regex escape `\\{}`", e)),
+                None => return Err(String::from("trailing `\\` in regex")),
+            },
+            '.' => bytes.push(None),
+            '*' | '+' | '?' | '|' | '(' | ')' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                return Err(format!("unsupported regex token `{}` (escape it to match the literal byte)", c));
+            },
+            _ => {
+                let mut buf = [0u8; 4];
+                bytes.extend(c.encode_utf8(&mut buf).bytes().map(Some));
+            },
+        }
+    }
+    Ok((bytes, nocase))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::signatures::{compile_signature, SignatureRule};
+    use trf_common::{SIG_LOC_HEADER, SIG_DIR_INBOUND, SIG_DROP};
+
+    fn rule(pattern: Option<&str>, regex: Option<&str>, nocase: bool) -> SignatureRule {
+        SignatureRule {
+            id: 1,
+            pattern: pattern.map(String::from),
+            regex: regex.map(String::from),
+            nocase,
+            location: String::from("header"),
+            direction: String::from("Inbound"),
+            action: String::from("drop"),
+        }
+    }
+
+    #[test]
+    fn pattern_test() {
+        let sig = compile_signature(&rule(Some("${jndi:rmi\\x3a"), None, false)).unwrap();
+        assert_eq!(sig.len, 11);
+        assert_eq!(&sig.pattern[..11], b"${jndi:rmi:");
+        assert!(sig.mask[..11].iter().all(|m| *m == 0xFF));
+        assert_eq!((sig.location, sig.direction, sig.action), (SIG_LOC_HEADER, SIG_DIR_INBOUND, SIG_DROP));
+    }
+
+    #[test]
+    fn regex_test() {
+        let sig = compile_signature(&rule(None, Some("(?i)\\$\\{j.di"), false)).unwrap();
+        assert_eq!(sig.len, 6);
+        assert_eq!(&sig.pattern[..6], &[b'$', b'{', b'J', 0, b'D', b'I']);
+        assert_eq!(&sig.mask[..6], &[0xFF, 0xFF, 0xDF, 0, 0xDF, 0xDF]);
+
+        assert!(compile_signature(&rule(None, Some("${jndi"), false)).is_err());
+        assert!(compile_signature(&rule(None, Some("jndi:(ldap|rmi)"), false)).is_err());
+        assert!(compile_signature(&rule(Some("x"), Some("x"), false)).is_err());
+    }
+}
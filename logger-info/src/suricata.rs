@@ -0,0 +1,256 @@
+use std::fs;
+use log::{debug, info, warn};
+use trf_common::{Signature, SIG_LOC_HEADER, SIG_LOC_URI, SIG_LOC_BODY, SIG_LOC_PAYLOAD,
+    SIG_DIR_OUTBOUND, SIG_DIR_INBOUND, SIG_ALERT, SIG_DROP};
+use crate::signatures::build_signature;
+
+/** Suricata/Snort rules (subset):
+ *  action proto src sport -> dst dport (option; option; ...)
+ *
+ * Supported options:
+ *      content (single, `|hex|` bytes) , nocase , msg , sid
+ *      flow (to_server / from_client => Inbound ; to_client / from_server => Outbound)
+ *      http sticky buffers (http.header, http.uri, http.request_body, ...) and
+ *      legacy content modifiers (http_header, http_uri, http_client_body, ...)
+ *
+ * eLogJ protects the servers behind the interface, requests sent to them
+ * (to_server) are inbound traffic. Options that only describe the rule
+ * (rev, classtype, reference, ...) are ignored, any other option rejects the
+ * rule so that a detection is never silently weakened. Since a rule holds
+ * a single content, buffers apply to it regardless of their position.
+**/
+#[derive(Debug)]
+pub struct SuricataRule {
+    pub sid: u32,
+    pub msg: String,
+    pub signature: Signature,
+}
+
+// Options ignored (no effect on matching)
+const IGNORED: [&str; 8] = ["rev", "classtype", "reference", "metadata", "priority", "target", "gid", "fast_pattern"];
+
+// Reads a rules file, rules that fail to parse are logged (warn) and skipped
+pub fn load_rules(file: &str) -> Vec<SuricataRule> {
+    let content = fs::read_to_string(file).expect("Could not open suricata rules file.");
+    let mut rules: Vec<SuricataRule> = Vec::new();
+    for (n, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_rule(line) {
+            Ok(rule) => rules.push(rule),
+            Err(e) => warn!("suricata: {}:{}: rule skipped - {}", file, n + 1, e),
+        }
+    }
+    info!("suricata: {} rule(s) loaded from {}", rules.len(), file);
+    for rule in &rules {
+        debug!("suricata: sid {} - {}", rule.sid, rule.msg);
+    }
+    rules
+}
+
+pub fn parse_rule(line: &str) -> Result<SuricataRule, String> {
+    let open = line.find('(').ok_or("missing rule options `(...)`")?;
+    let close = line.rfind(')').ok_or("missing closing `)`")?;
+    let header: Vec<&str> = line[..open].split_whitespace().collect();
+    if header.len() != 7 {
+        return Err(format!("invalid rule header `{}`", line[..open].trim()));
+    }
+
+    let action = match header[0] {
+        "alert" => SIG_ALERT,
+        "drop" | "reject" => SIG_DROP,
+        a => return Err(format!("unsupported action `{}`", a)),
+    };
+    let mut location = match header[1] {
+        "tcp" | "http" | "ldap" => SIG_LOC_PAYLOAD,
+        p => return Err(format!("unsupported protocol `{}`", p)),
+    };
+    if header[4] != "->" {
+        return Err(format!("unsupported direction `{}` (only `->`)", header[4]));
+    }
+    let mut direction = SIG_DIR_INBOUND;
+
+    let mut sid: Option<u32> = None;
+    let mut msg = String::new();
+    let mut content: Option<Vec<Option<u8>>> = None;
+    let mut nocase = false;
+
+    for option in split_options(&line[open + 1..close])? {
+        let (key, value) = match option.find(':') {
+            Some(i) => (option[..i].trim(), Some(option[i + 1..].trim())),
+            None => (option.trim(), None),
+        };
+        match key {
+            "msg" => msg = unescape(&unquote(value.unwrap_or(""))?),
+            "sid" => {
+                let v = value.ok_or("`sid` without value")?;
+                sid = Some(v.parse::<u32>().map_err(|_| format!("invalid sid `{}`", v))?);
+            },
+            "content" => {
+                if content.is_some() {
+                    return Err(String::from("multiple `content` matches are not supported"));
+                }
+                let v = value.ok_or("`content` without value")?;
+                if v.starts_with('!') {
+                    return Err(String::from("negated `content` is not supported"));
+                }
+                content = Some(parse_content(&unquote(v)?)?);
+            },
+            "nocase" => nocase = true,
+            "flow" => {
+                for flag in value.unwrap_or("").split(',').map(|f| f.trim()) {
+                    match flag {
+                        "to_server" | "from_client" => direction = SIG_DIR_INBOUND,
+                        "to_client" | "from_server" => direction = SIG_DIR_OUTBOUND,
+                        "established" | "stateless" => {},
+                        f => return Err(format!("unsupported flow option `{}`", f)),
+                    }
+                }
+            },
+            "http.header" | "http_header" | "http.header.raw" | "http_raw_header" => location = SIG_LOC_HEADER,
+            "http.uri" | "http_uri" | "http.uri.raw" | "http_raw_uri" => location = SIG_LOC_URI,
+            "http.request_body" | "http_client_body" | "http.response_body" | "http_server_body"
+                | "file.data" | "file_data" => location = SIG_LOC_BODY,
+            k if IGNORED.contains(&k) => {},
+            k => return Err(format!("unsupported keyword `{}`", k)),
+        }
+    }
+
+    let sid = sid.ok_or("missing `sid`")?;
+    let content = content.ok_or(format!("sid {}: missing `content`", sid))?;
+    let signature = build_signature(sid, &content, nocase, location, direction, action)
+        .map_err(|e| format!("sid {}: {}", sid, e))?;
+
+    Ok(SuricataRule { sid, msg, signature })
+}
+
+// Splits options on `;`, skipping quoted/escaped separators
+fn split_options(options: &str) -> Result<Vec<String>, String> {
+    let mut res: Vec<String> = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    let mut chars = options.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                cur.push(c);
+                if let Some(e) = chars.next() {
+                    cur.push(e);
+                }
+            },
+            '"' => {
+                quoted = !quoted;
+                cur.push(c);
+            },
+            ';' if !quoted => {
+                if !cur.trim().is_empty() {
+                    res.push(cur.trim().to_string());
+                }
+                cur.clear();
+            },
+            _ => cur.push(c),
+        }
+    }
+    if quoted {
+        return Err(String::from("unterminated quoted value"));
+    }
+    if !cur.trim().is_empty() {
+        res.push(cur.trim().to_string());
+    }
+    Ok(res)
+}
+
+// "value" --> value (escapes are kept for parse_content)
+fn unquote(value: &str) -> Result<String, String> {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        return Ok(value[1..value.len() - 1].to_string());
+    }
+    Err(format!("expected a quoted value, found `{}`", value))
+}
+
+// msg: `\"` `\;` `\\` escapes
+fn unescape(value: &str) -> String {
+    let mut res = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            if let Some(e) = chars.next() {
+                res.push(e);
+            }
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+// content: literal bytes, `|24 7B|` hex bytes and `\"` `\;` `\\` `\:` escapes
+fn parse_content(content: &str) -> Result<Vec<Option<u8>>, String> {
+    let mut bytes: Vec<Option<u8>> = Vec::new();
+    let mut hex = false;
+    let mut digits = String::new();
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        if hex {
+            if c == '|' {
+                hex = false;
+            } else if c.is_ascii_hexdigit() {
+                digits.push(c);
+                if digits.len() == 2 {
+                    bytes.push(Some(u8::from_str_radix(&digits, 16).unwrap()));
+                    digits.clear();
+                }
+            } else if c != ' ' {
+                return Err(format!("invalid hex byte `{}` in content", c));
+            }
+        } else if c == '|' {
+            hex = true;
+        } else if c == '\\' {
+            match chars.next() {
+                Some(e) if e == '"' || e == ';' || e == '\\' || e == ':' => bytes.push(Some(e as u8)),
+                Some(e) => return Err(format!("invalid escape `\\{}` in content", e)),
+                None => return Err(String::from("trailing `\\` in content")),
+            }
+        } else {
+            let mut buf = [0u8; 4];
+            bytes.extend(c.encode_utf8(&mut buf).bytes().map(Some));
+        }
+    }
+    if hex || !digits.is_empty() {
+        return Err(String::from("unterminated hex bytes in content"));
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::suricata::parse_rule;
+    use trf_common::{SIG_LOC_HEADER, SIG_LOC_URI, SIG_DIR_INBOUND, SIG_ALERT};
+
+    #[test]
+    fn parse_rule_test() {
+        let rule = parse_rule(r#"alert http any any -> $HOME_NET any (msg:"Log4Shell \; JNDI"; flow:established,to_server; content:"|24 7b|jndi:"; http.header; nocase; classtype:attempted-admin; sid:1000001; rev:2;)"#).unwrap();
+        assert_eq!(rule.sid, 1000001);
+        assert_eq!(rule.msg, "Log4Shell ; JNDI");
+        assert_eq!(rule.signature.id, 1000001);
+        assert_eq!(rule.signature.len, 7);
+        assert_eq!(&rule.signature.pattern[..7], b"${JNDI:");
+        assert_eq!((rule.signature.location, rule.signature.direction, rule.signature.action),
+            (SIG_LOC_HEADER, SIG_DIR_INBOUND, SIG_ALERT));
+
+        let rule = parse_rule(r#"alert http any any -> any any (content:"${jndi"; http_uri; sid:2;)"#).unwrap();
+        assert_eq!(rule.signature.location, SIG_LOC_URI);
+    }
+
+    #[test]
+    fn unsupported_test() {
+        let err = parse_rule(r#"alert http any any -> any any (content:"jndi"; pcre:"/ldap/i"; sid:3;)"#).unwrap_err();
+        assert!(err.contains("`pcre`"));
+        assert!(parse_rule(r#"alert http any any -> any any (content:"${"; content:"jndi"; sid:4;)"#).is_err());
+        assert!(parse_rule(r#"alert http any any -> any any (content:"jndi";)"#).is_err());
+        assert!(parse_rule(r#"pass http any any -> any any (content:"jndi"; sid:5;)"#).is_err());
+        assert!(parse_rule(r#"alert http any any <> any any (content:"jndi"; sid:6;)"#).is_err());
+    }
+}
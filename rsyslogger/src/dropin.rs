@@ -0,0 +1,226 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use serde::{Serialize, Deserialize};
+
+/** rsyslog drop-in:
+ * Generated from the rule file (`rsyslog:`).
+ * An existing drop-in is moved to `<dropin>.elogj-backup` and moved back
+ * on exit (same bytes and permissions), otherwise ours is removed.
+ * A backup left by a previous run (crash) is kept as the original.
+ *
+ * Installed when `forward` is set, messages of the (first) syslog sink are
+ * forwarded by omfwd (host, port, protocol udp | tcp | tls).
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RsyslogConfig {
+    pub dropin: String,
+    pub forward: Option<Forward>,
+}
+
+impl Default for RsyslogConfig {
+    fn default() -> Self {
+        RsyslogConfig {
+            dropin: String::from("/etc/rsyslog.d/0-filefwd.conf"),
+            forward: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Forward {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub ca_file: Option<String>,    // tls only
+}
+
+fn default_port() -> u16 {
+    514
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Udp,
+    Tcp,
+    Tls,
+}
+
+const BACKUP_EXT: &str = "elogj-backup";
+
+// Values end up in quoted RainerScript strings
+fn check_value(name: &str, value: &str) -> Result<(), String> {
+    if value.is_empty() || value.chars().any(|c| c == '"' || c == '\\' || c.is_whitespace() || c.is_control()) {
+        return Err(format!("rsyslog: invalid {} `{}`", name, value));
+    }
+    Ok(())
+}
+
+fn check_path(name: &str, path: &str) -> Result<(), String> {
+    check_value(name, path)?;
+    if !Path::new(path).is_absolute() {
+        return Err(format!("rsyslog: {} must be an absolute path (`{}`)", name, path));
+    }
+    Ok(())
+}
+
+impl RsyslogConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        check_path("dropin", &self.dropin)?;
+        let fwd = self.forward.as_ref().ok_or("rsyslog: the drop-in requires `forward`")?;
+        check_value("forward host", &fwd.host)?;
+        if fwd.port == 0 {
+            return Err(String::from("rsyslog: invalid forward port 0"));
+        }
+        match (&fwd.ca_file, fwd.protocol) {
+            (Some(ca), Protocol::Tls) => check_path("forward ca_file", ca),
+            (None, Protocol::Tls) => Err(String::from("rsyslog: tls forward requires `ca_file`")),
+            (Some(_), _) => Err(String::from("rsyslog: `ca_file` is only used with tls")),
+            (None, _) => Ok(()),
+        }
+    }
+
+    // Drop-in content, messages are selected by tag (syslog programname)
+    pub fn render(&self, tag: &str) -> Result<String, String> {
+        self.validate()?;
+        check_value("syslog tag", tag)?;
+        let action = match &self.forward {
+            Some(fwd) => {
+                let mut action = format!("action(type=\"omfwd\" target=\"{}\" port=\"{}\"", fwd.host, fwd.port);
+                match fwd.protocol {
+                    Protocol::Udp => action.push_str(" protocol=\"udp\""),
+                    Protocol::Tcp => action.push_str(" protocol=\"tcp\""),
+                    Protocol::Tls => action.push_str(&format!(" protocol=\"tcp\"\n        \
+                        StreamDriver=\"gtls\" StreamDriverMode=\"1\" StreamDriverAuthMode=\"x509/name\"\n        \
+                        StreamDriver.CAFile=\"{}\"", fwd.ca_file.as_deref().unwrap_or_default())),
+                }
+                action.push_str("\n        queue.type=\"linkedlist\" action.resumeRetryCount=\"-1\")");
+                action
+            },
+            None => unreachable!("validated forward"),
+        };
+        Ok(format!("# Generated by eLogJ, restored on exit\n\
+            if ($programname == \"{}\") then {{\n    {}\n}}\n", tag, action))
+    }
+}
+
+pub struct Dropin {
+    path: PathBuf,
+    backup: Option<PathBuf>,
+}
+
+impl Dropin {
+    // Writes the drop-in (backing up an existing one), rsyslog is not restarted
+    pub fn install(config: &RsyslogConfig, tag: &str) -> Result<Dropin, String> {
+        let content = config.render(tag)?;
+        let path = PathBuf::from(&config.dropin);
+        let backup = PathBuf::from(format!("{}.{}", config.dropin, BACKUP_EXT));
+
+        let backup = if backup.exists() {
+            Some(backup)    // left by a previous run: holds the original drop-in
+        } else if path.exists() {
+            fs::rename(&path, &backup).map_err(|e| format!("rsyslog: backup {}: {}", path.display(), e))?;
+            Some(backup)
+        } else {
+            None
+        };
+        let dropin = Dropin { path, backup };
+        if let Err(e) = fs::write(&dropin.path, content) {
+            let _ = dropin.restore();
+            return Err(format!("rsyslog: write {}: {}", dropin.path.display(), e));
+        }
+        Ok(dropin)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Puts back the original drop-in (or removes ours)
+    pub fn restore(&self) -> Result<(), String> {
+        match &self.backup {
+            Some(backup) => fs::rename(backup, &self.path)
+                .map_err(|e| format!("rsyslog: restore {}: {}", self.path.display(), e)),
+            None => match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound =>
+                    Err(format!("rsyslog: remove {}: {}", self.path.display(), e)),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+// rsyslogd config check (-N1), Ok if rsyslogd isn't installed
+pub fn check_rsyslogd() -> Result<(), String> {
+    match Command::new("rsyslogd").arg("-N1").output() {
+        Ok(out) if !out.status.success() => Err(format!("rsyslog: invalid config: {}",
+            String::from_utf8_lossy(&out.stderr).trim())),
+        _ => Ok(()),
+    }
+}
+
+pub fn restart_rsyslog() -> Result<(), String> {
+    match Command::new("systemctl").args(["restart", "rsyslog"]).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("rsyslog: restart failed ({})", status)),
+        Err(e) => Err(format!("rsyslog: restart failed ({})", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dropin::{Dropin, Forward, Protocol, RsyslogConfig};
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn render_test() {
+        let mut config = RsyslogConfig::default();
+        assert!(config.validate().is_err());
+        config.forward = Some(Forward { host: String::from("192.168.1.54"), port: 514, protocol: Protocol::Udp, ca_file: None });
+        assert_eq!(config.render("elogj.info").unwrap(), "# Generated by eLogJ, restored on exit\n\
+            if ($programname == \"elogj.info\") then {\n    action(type=\"omfwd\" target=\"192.168.1.54\" port=\"514\" \
+            protocol=\"udp\"\n        queue.type=\"linkedlist\" action.resumeRetryCount=\"-1\")\n}\n");
+
+        config.forward = Some(Forward { host: String::from("10.0.0.5"), port: 6514, protocol: Protocol::Tls, ca_file: None });
+        assert!(config.validate().is_err());
+        config.forward.as_mut().unwrap().ca_file = Some(String::from("/etc/ssl/ca.pem"));
+        let dropin = config.render("elogj.info").unwrap();
+        assert!(dropin.contains("target=\"10.0.0.5\" port=\"6514\" protocol=\"tcp\""));
+        assert!(dropin.contains("StreamDriver.CAFile=\"/etc/ssl/ca.pem\""));
+
+        config.forward.as_mut().unwrap().host = String::from("10.0.0.5\" port=\"1");
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn restore_test() {
+        let dir = std::env::temp_dir().join(format!("elogj-dropin-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("0-filefwd.conf");
+        fs::write(&path, "original\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let config = RsyslogConfig {
+            dropin: path.display().to_string(),
+            forward: Some(Forward { host: String::from("127.0.0.1"), port: 514, protocol: Protocol::Tcp, ca_file: None }),
+        };
+        let dropin = Dropin::install(&config, "elogj.info").unwrap();
+        assert!(fs::read_to_string(&path).unwrap().contains("omfwd"));
+        dropin.restore().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original\n");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o640);
+
+        fs::remove_file(&path).unwrap();
+        let dropin = Dropin::install(&config, "elogj.info").unwrap();
+        dropin.restore().unwrap();
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::Path;
+use std::time::Duration;
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono::format::{Item, StrftimeItems};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Map, Value};
+use crate::sink::{Message, Severity, Sink};
+use crate::syslog::hostname;
+
+/** Elasticsearch / OpenSearch sink:
+ * Events are indexed as ECS documents (source.ip, destination.port,
+ * event.action, rule.id, ... and elogj.* for the rest) with the `_bulk` API.
+ * `index` is a strftime template of the event time (UTC), ex: elogj-%Y.%m.%d
+ *
+ *  endpoint: http://localhost:9200   --> POST <endpoint>/_bulk (application/x-ndjson)
+ *  no endpoint                       --> <dir>/<index>.ndjson, to be loaded later:
+ *      curl -H 'Content-Type: application/x-ndjson' --data-binary @elogj-2026.10.19.ndjson <endpoint>/_bulk
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ElasticConfig {
+    pub endpoint: Option<String>,
+    pub dir: String,
+    pub index: String,
+    pub headers: BTreeMap<String, String>,  // ex: Authorization: ApiKey ...
+    pub batch: usize,
+    pub timeout: u64,   // seconds
+}
+
+impl Default for ElasticConfig {
+    fn default() -> Self {
+        ElasticConfig {
+            endpoint: None,
+            dir: String::from("/var/log/elogj/bulk"),
+            index: String::from("elogj-%Y.%m.%d"),
+            headers: BTreeMap::new(),
+            batch: 500,
+            timeout: 10,
+        }
+    }
+}
+
+pub const ECS_VERSION: &str = "8.11.0";
+
+// Fields holding numbers (ports, ids)
+fn number(value: &str) -> Value {
+    value.parse::<u64>().map(Value::from).unwrap_or_else(|_| Value::from(value))
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Notice => "notice",
+        Severity::Warning => "warning",
+        Severity::Critical => "critical",
+    }
+}
+
+fn event_time(msg: &Message) -> DateTime<Utc> {
+    DateTime::from_timestamp_nanos(msg.time_ns as i64)
+}
+
+// ECS document of an event (built from the structured fields)
+pub fn document(msg: &Message, host: &str) -> Value {
+    let field = |name: &str| msg.fields.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str());
+    let alert = msg.info.severity >= Severity::Warning;
+    let mut category = vec!["network"];
+    if alert {
+        category.push("intrusion_detection");
+    }
+    let mut event = json!({
+        "kind": if alert { "alert" } else { "event" },
+        "category": category,
+        "type": ["connection", if msg.info.dropped { "denied" } else { "allowed" }],
+        "action": field("VERDICT").unwrap_or("-").to_lowercase(),
+    });
+    let mut doc = json!({
+        "@timestamp": event_time(msg).to_rfc3339_opts(SecondsFormat::Micros, true),
+        "message": msg.text,
+        "ecs": { "version": ECS_VERSION },
+        "log": { "level": level(msg.info.severity) },
+        "network": {
+            "type": "ipv4",
+            "transport": field("PROTOCOL").unwrap_or("-"),
+            "direction": field("DIRECTION").unwrap_or("-").to_lowercase(),
+        },
+        "observer": { "hostname": host, "vendor": "eLogJ", "product": "eLogJ", "type": "ids" },
+    });
+    let mut source = Map::new();
+    let mut destination = Map::new();
+    let mut rule = Map::new();
+    let mut elogj = Map::new();
+    for (name, value) in &msg.fields {
+        match *name {
+            "SRC" => { source.insert(String::from("ip"), Value::from(value.as_str())); },
+            "SRC_PORT" => { source.insert(String::from("port"), number(value)); },
+            "DST" => { destination.insert(String::from("ip"), Value::from(value.as_str())); },
+            "DST_PORT" => { destination.insert(String::from("port"), number(value)); },
+            "REASON" => {
+                event["reason"] = Value::from(value.as_str());
+                rule.insert(String::from("name"), Value::from(value.as_str()));
+            },
+            "RULE_ID" => { rule.insert(String::from("id"), Value::from(value.as_str())); },
+            "SIGNATURE_ID" => { elogj.insert(String::from("signature_id"), number(value)); },
+            "DETECTIONS" => { elogj.insert(String::from("detections"), value.split(',').collect()); },
+            "FLOW_ID" => { elogj.insert(String::from("flow_id"), Value::from(value.as_str())); },
+            "ALERT" => { elogj.insert(String::from("alert"), Value::from(true)); },
+            "PROTOCOL" | "DIRECTION" | "VERDICT" => {},
+            _ => { elogj.insert(name.to_lowercase(), Value::from(value.as_str())); },
+        }
+    }
+    doc["event"] = event;
+    doc["source"] = Value::Object(source);
+    doc["destination"] = Value::Object(destination);
+    if !rule.is_empty() {
+        doc["rule"] = Value::Object(rule);
+    }
+    if !elogj.is_empty() {
+        doc["elogj"] = Value::Object(elogj);
+    }
+    doc
+}
+
+// Index names: lowercase, none of \/*?"<>| ,# and not starting with -_+
+fn check_index(index: &str) -> Result<(), String> {
+    if index.is_empty() || index.starts_with(['-', '_', '+']) || index.contains(['\\', '/', '*', '?', '"', '<', '>', '|', ' ', ',', '#'])
+        || index.chars().any(|c| c.is_uppercase()) || index == "." || index == ".." {
+        return Err(format!("elastic: invalid index name `{}`", index));
+    }
+    Ok(())
+}
+
+pub struct ElasticSink {
+    config: ElasticConfig,
+    agent: ureq::Agent,
+    host: String,
+    pending: Vec<(String, String)>,     // (index, action + document lines)
+}
+
+impl ElasticSink {
+    pub fn new(config: &ElasticConfig) -> Result<ElasticSink, String> {
+        if StrftimeItems::new(&config.index).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("elastic: invalid index template `{}`", config.index));
+        }
+        check_index(&Utc::now().format(&config.index).to_string())?;
+        match &config.endpoint {
+            Some(endpoint) if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") =>
+                return Err(format!("elastic: invalid endpoint `{}`", endpoint)),
+            Some(_) => {},
+            None => {
+                if !Path::new(&config.dir).is_absolute() {
+                    return Err(format!("elastic: dir must be an absolute path (`{}`)", config.dir));
+                }
+                fs::DirBuilder::new().recursive(true).mode(0o700).create(&config.dir)
+                    .map_err(|e| format!("elastic: create {}: {}", config.dir, e))?;
+            },
+        }
+        Ok(ElasticSink {
+            config: config.clone(),
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(config.timeout.max(1))).build(),
+            host: hostname(),
+            pending: Vec::new(),
+        })
+    }
+
+    fn index(&self, msg: &Message) -> String {
+        event_time(msg).format(&self.config.index).to_string()
+    }
+
+    // Appends the batch to <dir>/<index>.ndjson
+    fn write_files(&self, pending: &[(String, String)]) -> Result<(), String> {
+        let mut by_index: BTreeMap<&str, String> = BTreeMap::new();
+        for (index, lines) in pending {
+            by_index.entry(index).or_default().push_str(lines);
+        }
+        for (index, lines) in by_index {
+            let path = Path::new(&self.config.dir).join(format!("{}.ndjson", index));
+            OpenOptions::new().create(true).append(true).mode(0o600).open(&path)
+                .and_then(|mut f| f.write_all(lines.as_bytes()))
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    // The bulk API answers 200 even if documents are rejected (errors: true)
+    fn post(&self, endpoint: &str, pending: &[(String, String)]) -> Result<(), String> {
+        let body: String = pending.iter().map(|(_, lines)| lines.as_str()).collect();
+        let mut request = self.agent.post(&format!("{}/_bulk", endpoint.trim_end_matches('/')))
+            .set("Content-Type", "application/x-ndjson");
+        for (name, value) in &self.config.headers {
+            request = request.set(name, value);
+        }
+        let response = request.send_string(&body).map_err(|e| e.to_string())?
+            .into_string().map_err(|e| format!("bulk response: {}", e))?;
+        let response: Value = serde_json::from_str(&response).map_err(|e| format!("bulk response: {}", e))?;
+        if response["errors"] != Value::Bool(true) {
+            return Ok(());
+        }
+        let errors: Vec<&Value> = response["items"].as_array().into_iter().flatten()
+            .filter_map(|item| item.as_object()?.values().next()?.get("error"))
+            .collect();
+        Err(format!("{} document(s) rejected ({})", errors.len(),
+            errors.first().and_then(|e| e["reason"].as_str()).unwrap_or("unknown error")))
+    }
+
+    fn send(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut self.pending);    // not retried
+        let res = match &self.config.endpoint {
+            Some(endpoint) => self.post(endpoint, &pending),
+            None => self.write_files(&pending),
+        };
+        res.map_err(|e| format!("{} document(s): {}", pending.len(), e))
+    }
+}
+
+impl Sink for ElasticSink {
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        let index = self.index(msg);
+        let lines = format!("{}\n{}\n", json!({ "create": { "_index": index } }), document(msg, &self.host));
+        self.pending.push((index, lines));
+        if self.pending.len() >= self.config.batch.max(1) {
+            return self.send();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elastic::{document, ElasticConfig, ElasticSink};
+    use crate::sink::{Message, Sink};
+    use crate::sink::test_util::http_server;
+    use serde_json::Value;
+
+    #[test]
+    fn document_test() {
+        let doc = document(&Message::test_drop("a --> b - DROP"), "sensor");
+        assert_eq!(doc["@timestamp"], "2026-10-19T08:09:10.123000Z");
+        assert_eq!((doc["source"]["ip"].as_str(), doc["source"]["port"].as_u64()), (Some("10.0.0.1"), Some(40000)));
+        assert_eq!((doc["destination"]["ip"].as_str(), doc["destination"]["port"].as_u64()), (Some("172.17.0.2"), Some(8080)));
+        assert_eq!((doc["event"]["kind"].as_str(), doc["event"]["action"].as_str()), (Some("alert"), Some("drop")));
+        assert_eq!(doc["event"]["type"][1], "denied");
+        assert_eq!((doc["rule"]["id"].as_str(), doc["rule"]["name"].as_str()), (Some("3"), Some("jndi_request")));
+        assert_eq!((doc["network"]["transport"].as_str(), doc["network"]["direction"].as_str()), (Some("tcp"), Some("inbound")));
+        assert_eq!(doc["elogj"]["detections"][1], "jndi");
+
+        // Offline NDJSON files
+        let dir = std::env::temp_dir().join(format!("elogj-bulk-{}", std::process::id()));
+        let config = ElasticConfig { dir: dir.display().to_string(), index: String::from("elogj-%Y.%m"), ..ElasticConfig::default() };
+        let mut sink = ElasticSink::new(&config).unwrap();
+        sink.write(&Message::test_drop("a --> b - DROP")).unwrap();
+        sink.flush().unwrap();
+        let ndjson = std::fs::read_to_string(dir.join("elogj-2026.10.ndjson")).unwrap();
+        let lines: Vec<Value> = ndjson.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["create"]["_index"], "elogj-2026.10");
+        assert_eq!(lines[1]["event"]["action"], "drop");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(ElasticSink::new(&ElasticConfig { index: String::from("ELOGJ-%Y"), ..config.clone() }).is_err());
+        assert!(ElasticSink::new(&ElasticConfig { index: String::from("elogj-%Q"), ..config }).is_err());
+    }
+
+    // Cluster stand-in: accepts the first bulk request, rejects a document of the second
+    #[test]
+    fn bulk_test() {
+        let (addr, rx) = http_server(|n| match n {
+            0 => ("200 OK", String::from(r#"{"took":1,"errors":false,"items":[]}"#)),
+            _ => ("200 OK", String::from(r#"{"took":1,"errors":true,"items":[{"create":{"status":400,"error":{"type":"mapper_parsing_exception","reason":"failed to parse"}}}]}"#)),
+        });
+        let config = ElasticConfig {
+            endpoint: Some(format!("http://{}/", addr)),
+            batch: 2,
+            ..ElasticConfig::default()
+        };
+
+        let mut sink = ElasticSink::new(&config).unwrap();
+        sink.write(&Message::test_drop("a --> b - DROP")).unwrap();
+        sink.write(&Message::test_drop("a --> b - DROP")).unwrap();    // batch full
+        let request = rx.recv().unwrap();
+        let body = String::from_utf8(request.body).unwrap();
+        assert!(request.line.starts_with("POST /_bulk "));
+        assert_eq!(request.headers["content-type"], "application/x-ndjson");
+        assert_eq!(body.lines().count(), 4);
+        assert!(body.starts_with("{\"create\":{\"_index\":\"elogj-2026.10.19\"}}\n"));
+
+        sink.write(&Message::test_drop("a --> b - DROP")).unwrap();
+        assert!(sink.flush().unwrap_err().contains("1 document(s) rejected (failed to parse)"));
+    }
+}
@@ -0,0 +1,249 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use chrono::Local;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::warn;
+use serde::{Serialize, Deserialize};
+use crate::sink::{Message, Sink};
+
+/** Rotating log files (file sink):
+ * Written by trf itself, rsyslog is not needed.
+ * The current file is rotated when it reaches `max_size` bytes or is older
+ * than `max_age` seconds (0: size only) and renamed `<name>.<YYYYmmdd-HHMMSS>`,
+ * then gzipped (`compress`). Only the `keep` newest rotated files are kept.
+ *
+ *  dir (0700) / name (mode, octal: "0600")
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileConfig {
+    pub dir: String,
+    pub name: String,
+    pub max_size: u64,
+    pub max_age: u64,
+    pub compress: bool,
+    pub keep: usize,
+    pub mode: String,
+}
+
+impl Default for FileConfig {
+    fn default() -> Self {
+        FileConfig {
+            dir: String::from("/var/log/elogj"),
+            name: String::from("elogj.log"),
+            max_size: 10 * 1024 * 1024,
+            max_age: 86400,
+            compress: true,
+            keep: 10,
+            mode: String::from("0600"),
+        }
+    }
+}
+
+impl FileConfig {
+    pub fn validate(&self) -> Result<u32, String> {
+        if !Path::new(&self.dir).is_absolute() {
+            return Err(format!("file: dir must be an absolute path (`{}`)", self.dir));
+        }
+        if self.name.is_empty() || self.name.contains('/') {
+            return Err(format!("file: invalid name `{}`", self.name));
+        }
+        if self.max_size == 0 {
+            return Err(String::from("file: max_size must be > 0"));
+        }
+        match u32::from_str_radix(self.mode.trim_start_matches("0o"), 8) {
+            Ok(mode) if mode & !0o777 == 0 => Ok(mode),
+            _ => Err(format!("file: invalid mode `{}` (octal, e.g. 0600)", self.mode)),
+        }
+    }
+}
+
+pub struct RotatingFile {
+    config: FileConfig,
+    mode: u32,
+    path: PathBuf,
+    file: File,
+    size: u64,
+    opened: SystemTime,
+}
+
+impl RotatingFile {
+    pub fn open(config: &FileConfig) -> Result<RotatingFile, String> {
+        let mode = config.validate()?;
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(&config.dir)
+            .map_err(|e| format!("file: create {}: {}", config.dir, e))?;
+        let path = Path::new(&config.dir).join(&config.name);
+        let (file, size, opened) = open_file(&path, mode)
+            .map_err(|e| format!("file: open {}: {}", path.display(), e))?;
+        Ok(RotatingFile { config: config.clone(), mode, path, file, size, opened })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size != 0 && self.is_due(line.len() as u64 + 1) {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn is_due(&self, len: u64) -> bool {
+        if self.size + len > self.config.max_size {
+            return true;
+        }
+        self.config.max_age != 0 && self.opened.elapsed()
+            .map(|age| age >= Duration::from_secs(self.config.max_age))
+            .unwrap_or(false)
+    }
+
+    // Renames the current file, reopens a new one then compresses and prunes the rotated ones
+    pub fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let mut rotated = PathBuf::from(format!("{}.{}", self.path.display(), Local::now().format("%Y%m%d-%H%M%S")));
+        let mut n = 1;
+        while rotated.exists() || gz_path(&rotated).exists() {
+            rotated = PathBuf::from(format!("{}.{}-{}", self.path.display(), Local::now().format("%Y%m%d-%H%M%S"), n));
+            n += 1;
+        }
+        fs::rename(&self.path, &rotated)?;
+        (self.file, self.size, self.opened) = open_file(&self.path, self.mode)?;
+
+        if self.config.compress {
+            if let Err(e) = compress(&rotated, self.mode) {
+                warn!("file: compress {}: {}", rotated.display(), e);
+            }
+        }
+        self.prune()
+    }
+
+    // Removes the oldest rotated files beyond `keep`
+    fn prune(&self) -> io::Result<()> {
+        let mut rotated: Vec<((String, u32), PathBuf)> = Vec::new();
+        for entry in fs::read_dir(&self.config.dir)? {
+            let entry = entry?;
+            if let Some(key) = rotated_key(&self.config.name, &entry.file_name().to_string_lossy()) {
+                rotated.push((key, entry.path()));
+            }
+        }
+        rotated.sort();
+        let excess = rotated.len().saturating_sub(self.config.keep);
+        for (_, path) in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+// <name>.<stamp>[-n][.gz] --> (stamp, n), rotations within the same second are numbered
+// Only rotate's stamp (YYYYmmdd-HHMMSS) matches, other <name>.* files are left alone
+fn rotated_key(name: &str, file: &str) -> Option<(String, u32)> {
+    let rest = file.strip_prefix(name)?.strip_prefix('.')?;
+    let rest = rest.strip_suffix(".gz").unwrap_or(rest);
+    let (stamp, n) = match rest.get(15..) {
+        Some("") => (rest, 0),
+        Some(n) => (&rest[..15], n.strip_prefix('-')?.parse().ok()?),
+        None => return None,
+    };
+    let (date, time) = stamp.split_once('-')?;
+    if date.len() != 8 || time.len() != 6 || !date.bytes().chain(time.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((stamp.to_string(), n))
+}
+
+// Append mode, the mode is also applied to an existing file
+fn open_file(path: &Path, mode: u32) -> io::Result<(File, u64, SystemTime)> {
+    let file = OpenOptions::new().create(true).append(true).mode(mode).open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(mode))?;
+    let meta = file.metadata()?;
+    let opened = if meta.len() == 0 { SystemTime::now() } else { meta.modified()? };
+    Ok((file, meta.len(), opened))
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.gz", path.display()))
+}
+
+fn compress(path: &Path, mode: u32) -> io::Result<()> {
+    let gz = gz_path(path);
+    let out = OpenOptions::new().create_new(true).write(true).mode(mode).open(&gz)?;
+    let mut encoder = GzEncoder::new(out, Compression::default());
+    io::copy(&mut File::open(path)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::remove_file(path)
+}
+
+impl Sink for RotatingFile {
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        self.write_line(&format!("{} {}", Local::now().to_rfc3339(), msg.text))
+            .map_err(|e| format!("{}: {}", self.path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::{FileConfig, RotatingFile, rotated_key};
+    use flate2::read::GzDecoder;
+    use std::fs;
+    use std::io::Read;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn rotate_test() {
+        let dir = std::env::temp_dir().join(format!("elogj-file-{}", std::process::id()));
+        let config = FileConfig {
+            dir: dir.display().to_string(),
+            max_size: 64,
+            max_age: 0,
+            keep: 2,
+            ..FileConfig::default()
+        };
+        assert!(FileConfig { mode: String::from("0999"), ..config.clone() }.validate().is_err());
+        assert!(FileConfig { dir: String::from("logs"), ..config.clone() }.validate().is_err());
+
+        let mut file = RotatingFile::open(&config).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+        assert_eq!(fs::metadata(file.path()).unwrap().permissions().mode() & 0o777, 0o600);
+        for i in 0..8 {
+            file.write_line(&format!("{:02} 10.0.0.1 --> 10.0.0.2 - DROP", i)).unwrap();
+        }
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "06 10.0.0.1 --> 10.0.0.2 - DROP\n07 10.0.0.1 --> 10.0.0.2 - DROP\n");
+
+        // 3 rotations, the oldest one is pruned
+        let mut rotated: Vec<String> = Vec::new();
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            if name == "elogj.log" {
+                continue;
+            }
+            assert!(name.starts_with("elogj.log.") && name.ends_with(".gz"));
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            let mut text = String::new();
+            GzDecoder::new(fs::File::open(&path).unwrap()).read_to_string(&mut text).unwrap();
+            rotated.push(text);
+        }
+        rotated.sort();
+        assert_eq!(rotated, ["02 10.0.0.1 --> 10.0.0.2 - DROP\n03 10.0.0.1 --> 10.0.0.2 - DROP\n",
+            "04 10.0.0.1 --> 10.0.0.2 - DROP\n05 10.0.0.1 --> 10.0.0.2 - DROP\n"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotated_key_test() {
+        assert_eq!(rotated_key("elogj.log", "elogj.log.20240105-120000.gz"), Some((String::from("20240105-120000"), 0)));
+        assert_eq!(rotated_key("elogj.log", "elogj.log.20240105-120000-2"), Some((String::from("20240105-120000"), 2)));
+        assert_eq!(rotated_key("elogj.log", "elogj.log.bak"), None);
+        assert_eq!(rotated_key("elogj.log", "elogj.log.1.gz"), None);
+        assert_eq!(rotated_key("elogj.log", "elogj.log.20240105-120000.old"), None);
+        assert_eq!(rotated_key("elogj.log", "elogj.log.2024010a-120000"), None);
+    }
+}
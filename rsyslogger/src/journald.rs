@@ -0,0 +1,114 @@
+use std::os::unix::net::UnixDatagram;
+use serde::{Serialize, Deserialize};
+use crate::sink::{Message, Severity, Sink};
+
+/** journald sink:
+ * Native journal protocol (datagrams on the journald socket, no `logger`).
+ * Event fields are sent as ELOGJ_<name> (ELOGJ_SRC, ELOGJ_VERDICT, ...)
+ * next to MESSAGE, PRIORITY (from the severity) and SYSLOG_IDENTIFIER:
+ *
+ *  journalctl SYSLOG_IDENTIFIER=elogj ELOGJ_VERDICT=DROP
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournaldConfig {
+    pub socket: String,
+    pub identifier: String,
+}
+
+impl Default for JournaldConfig {
+    fn default() -> Self {
+        JournaldConfig {
+            socket: String::from("/run/systemd/journal/socket"),
+            identifier: String::from("elogj"),
+        }
+    }
+}
+
+// Syslog levels: info (6), notice (5), warning (4), crit (2)
+pub fn priority(severity: Severity) -> u8 {
+    match severity {
+        Severity::Info => 6,
+        Severity::Notice => 5,
+        Severity::Warning => 4,
+        Severity::Critical => 2,
+    }
+}
+
+// KEY=value\n, values holding a newline use the binary form: KEY\n<u64 le len>value\n
+fn push_field(buf: &mut Vec<u8>, key: &str, value: &str) {
+    buf.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+pub fn encode(msg: &Message, identifier: &str) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    push_field(&mut buf, "MESSAGE", &msg.text);
+    push_field(&mut buf, "PRIORITY", &priority(msg.info.severity).to_string());
+    push_field(&mut buf, "SYSLOG_IDENTIFIER", identifier);
+    for (name, value) in &msg.fields {
+        push_field(&mut buf, &format!("ELOGJ_{}", name), value);
+    }
+    buf
+}
+
+pub struct JournaldSink {
+    config: JournaldConfig,
+    sock: UnixDatagram,
+}
+
+impl JournaldSink {
+    pub fn new(config: &JournaldConfig) -> Result<JournaldSink, String> {
+        if config.identifier.is_empty() || config.identifier.contains(['\n', '=']) {
+            return Err(format!("journald: invalid identifier `{}`", config.identifier));
+        }
+        let sock = UnixDatagram::unbound().map_err(|e| format!("journald: {}", e))?;
+        Ok(JournaldSink { config: config.clone(), sock })
+    }
+}
+
+impl Sink for JournaldSink {
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        self.sock.send_to(&encode(msg, &self.config.identifier), &self.config.socket)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {}", self.config.socket, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::journald::{encode, JournaldConfig, JournaldSink};
+    use crate::sink::{Direction, Message, Severity, Sink};
+    use std::os::unix::net::UnixDatagram;
+    use std::time::Duration;
+
+    #[test]
+    fn journald_test() {
+        let path = std::env::temp_dir().join(format!("elogj-journal-{}", std::process::id()));
+        let server = UnixDatagram::bind(&path).unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let msg = Message {
+            fields: vec![("VERDICT", String::from("DROP")), ("SNIPPET", String::from("x\ny"))],
+            ..Message::test("a --> b - DROP", Severity::Critical, Direction::Inbound)
+        };
+        assert_eq!(encode(&msg, "elogj"), b"MESSAGE=a --> b - DROP\nPRIORITY=2\nSYSLOG_IDENTIFIER=elogj\n\
+            ELOGJ_VERDICT=DROP\nELOGJ_SNIPPET\n\x03\0\0\0\0\0\0\0x\ny\n");
+
+        let config = JournaldConfig { socket: path.display().to_string(), ..JournaldConfig::default() };
+        let mut sink = JournaldSink::new(&config).unwrap();
+        sink.write(&msg).unwrap();
+        let mut buf = [0u8; 512];
+        let len = server.recv(&mut buf).unwrap();
+        assert_eq!(&buf[..len], &encode(&msg, "elogj")[..]);
+        assert!(JournaldSink::new(&JournaldConfig { identifier: String::from("a=b"), ..config }).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,5 +1,17 @@
 use std::process::Command;
-use cmd_lib::run_cmd;
+
+pub mod syslog;
+pub mod dropin;
+use dropin::{RsyslogConfig, Dropin, check_rsyslogd, restart_rsyslog};
+pub mod elastic;
+pub mod file;
+pub mod journald;
+pub mod metrics;
+pub mod otlp;
+pub mod sink;
+pub mod socket;
+pub mod webhook;
+use sink::{SinkConfig, Sinks};
 
 // https://docs.rs/syslog/latest/syslog/
 
@@ -9,32 +21,59 @@ use cmd_lib::run_cmd;
 // (remote) res: https://serverfault.com/a/181440
 
 pub struct Rsysloggerd {
-    log_type: String
+    sinks: Sinks,
+    dropin: Option<Dropin>,
 }
 
-pub fn __init_rsysloggerd(log_type: String) -> Rsysloggerd {
-    Rsysloggerd {
-        log_type: log_type
-    }.__config_base()
+pub fn __init_rsysloggerd(sinks: &[SinkConfig], rsyslog: &RsyslogConfig) -> Rsysloggerd {
+    let rsysloggerd = Rsysloggerd {
+        sinks: Sinks::spawn(sinks).expect("Invalid sinks config"),
+        dropin: None,
+    };
+    match SinkConfig::dropin_tag(sinks, rsyslog) {
+        Some(tag) => rsysloggerd.__config_base(tag, rsyslog),
+        None => rsysloggerd,
+    }
 }
 
 impl Rsysloggerd {
-    fn __config_base(self) -> Self {
-        let log_type = format!("{}", self.log_type);
-        if run_cmd! {
-            sh rsyslogger/config.sh ${log_type}
-        }.is_err() {
-            println!("error running rsyslog config");
+    // Installs the rsyslog drop-in (forward), an invalid config is fatal (the original drop-in is restored first)
+    fn __config_base(mut self, tag: &str, rsyslog: &RsyslogConfig) -> Self {
+        let dropin = Dropin::install(rsyslog, tag).expect("Invalid rsyslog config");
+        if let Err(e) = check_rsyslogd() {
+            dropin.restore().expect("Could not restore rsyslog drop-in");
+            panic!("{}", e);
+        }
+        println!("rsyslog: {} written", dropin.path().display());
+        if let Err(e) = restart_rsyslog() {
+            println!("{}", e);
         }
+        self.dropin = Some(dropin);
         self
     }
 
+    // Output handle for the event readers
+    pub fn sinks(&self) -> Sinks {
+        self.sinks.clone()
+    }
+
+    // Flushes and stops the sinks, restores the rsyslog drop-in found at startup (log files are kept)
     pub fn __purge(&self) {
-        if run_cmd! {
-            rm /etc/rsyslog.d/0-filefwd.conf;
-            rm /tmp/elogj-info.log;
-        }.is_err() {
-            println!("error purging");
+        self.sinks.shutdown();
+        for worker in self.sinks.workers() {
+            if worker.dropped() != 0 {
+                println!("{}: {} message(s) dropped (queue full)", worker.name(), worker.dropped());
+            }
+        }
+        if let Some(dropin) = &self.dropin {
+            match dropin.restore() {
+                Ok(_) => {
+                    if let Err(e) = restart_rsyslog() {
+                        println!("{}", e);
+                    }
+                },
+                Err(e) => println!("{}", e),
+            }
         }
     }
 }
@@ -45,11 +84,3 @@ pub fn remote_log() {
         .spawn()
         .expect("sh command failed to start");
 }
-
-pub fn info_log(log: String) {
-    if run_cmd! {
-        logger -i -t elogj.info -p local6.info ${log}
-    }.is_err() {
-        println!("error local info log");
-    }
-}
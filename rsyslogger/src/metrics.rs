@@ -0,0 +1,204 @@
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use log::warn;
+use serde::{Serialize, Deserialize};
+
+/** Prometheus metrics (`metrics:`):
+ * Text exposition format (0.0.4) served on GET /metrics, disabled unless
+ * `listen` is set. There's no authentication: a bare port (`9469` , `:9469`)
+ * binds 127.0.0.1, other interfaces have to be named explicitly.
+ *
+ *  metrics:
+ *    listen: 127.0.0.1:9469
+**/
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub listen: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Counter,
+    Gauge,
+}
+
+#[derive(Debug, Clone)]
+struct Family {
+    name: String,
+    help: String,
+    kind: Kind,
+    samples: Vec<(String, u64)>,   // (labels , value)
+}
+
+// Metric families, in insertion order
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    families: Vec<Family>,
+}
+
+// Label values: `\`, `"` and line breaks
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+impl Metrics {
+    // Adds a sample, help and kind are taken from the first sample of the family
+    pub fn add(&mut self, kind: Kind, name: &str, help: &str, labels: &[(&str, &str)], value: u64) {
+        let labels = labels.iter().filter(|(k, _)| !k.is_empty())
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+        match self.families.iter_mut().find(|f| f.name == name) {
+            Some(family) => family.samples.push((labels, value)),
+            None => self.families.push(Family {
+                name: String::from(name),
+                help: String::from(help),
+                kind,
+                samples: vec![(labels, value)],
+            }),
+        }
+    }
+
+    pub fn counter(&mut self, name: &str, help: &str, labels: &[(&str, &str)], value: u64) {
+        self.add(Kind::Counter, name, help, labels, value);
+    }
+
+    pub fn gauge(&mut self, name: &str, help: &str, labels: &[(&str, &str)], value: u64) {
+        self.add(Kind::Gauge, name, help, labels, value);
+    }
+
+    pub fn render(&self) -> String {
+        let mut res = String::new();
+        for family in &self.families {
+            let kind = match family.kind {
+                Kind::Counter => "counter",
+                Kind::Gauge => "gauge",
+            };
+            let _ = writeln!(res, "# HELP {} {}", family.name, family.help.replace('\\', "\\\\").replace('\n', "\\n"));
+            let _ = writeln!(res, "# TYPE {} {}", family.name, kind);
+            for (labels, value) in &family.samples {
+                if labels.is_empty() {
+                    let _ = writeln!(res, "{} {}", family.name, value);
+                } else {
+                    let _ = writeln!(res, "{}{{{}}} {}", family.name, labels, value);
+                }
+            }
+        }
+        res
+    }
+}
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+fn respond(stream: TcpStream, render: &dyn Fn() -> String) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    loop {  // headers (ignored)
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next().map(|p| p.split('?').next().unwrap_or(p))) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render()),
+        (Some("GET"), _) => ("404 Not Found", String::from("see /metrics\n")),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+    let mut stream = stream;
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, CONTENT_TYPE, body.len(), body)?;
+    stream.flush()
+}
+
+const DEFAULT_HOST: &str = "127.0.0.1";
+
+// `listen` as a socket address, loopback unless a host is given
+fn listen_addr(listen: &str) -> String {
+    match listen.trim_start_matches(':').parse::<u16>() {
+        Ok(port) => format!("{}:{}", DEFAULT_HOST, port),
+        Err(_) => listen.to_string(),
+    }
+}
+
+// Serves render() on `listen` (accepted by thread elogj-metrics, a short-lived thread per connection
+// so a stalled client doesn't hold the other scrapes back), returns the bound address
+pub fn serve(config: &MetricsConfig, render: impl Fn() -> String + Send + Sync + 'static) -> Result<Option<String>, String> {
+    let Some(listen) = &config.listen else {
+        return Ok(None);
+    };
+    let listen = listen_addr(listen);
+    let listener = TcpListener::bind(&listen).map_err(|e| format!("metrics: bind {}: {}", listen, e))?;
+    let addr = listener.local_addr().map(|a| a.to_string()).unwrap_or(listen);
+    let render = Arc::new(render);
+    thread::Builder::new()
+        .name(String::from("elogj-metrics"))
+        .spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let render = render.clone();
+                let conn = thread::Builder::new()
+                    .name(String::from("elogj-metrics-conn"))
+                    .spawn(move || {
+                        if let Err(e) = respond(stream, &*render) {
+                            warn!("metrics: {}", e);
+                        }
+                    });
+                if let Err(e) = conn {
+                    warn!("metrics: connection thread: {}", e);
+                }
+            }
+        })
+        .map_err(|e| format!("metrics: server thread: {}", e))?;
+    Ok(Some(addr))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::{listen_addr, serve, Metrics, MetricsConfig};
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    #[test]
+    fn metrics_test() {
+        let mut metrics = Metrics::default();
+        metrics.counter("elogj_drops_total", "Dropped packets", &[("direction", "inbound")], 3);
+        metrics.counter("elogj_drops_total", "", &[("direction", "outbound")], 1);
+        metrics.gauge("elogj_map_entries", "Map entries", &[("map", "a\"b")], 7);
+        metrics.counter("elogj_lsm_denied_total", "Denied bpf() calls", &[("", "")], 0);
+        let text = metrics.render();
+        assert_eq!(text, "# HELP elogj_drops_total Dropped packets\n# TYPE elogj_drops_total counter\n\
+            elogj_drops_total{direction=\"inbound\"} 3\nelogj_drops_total{direction=\"outbound\"} 1\n\
+            # HELP elogj_map_entries Map entries\n# TYPE elogj_map_entries gauge\nelogj_map_entries{map=\"a\\\"b\"} 7\n\
+            # HELP elogj_lsm_denied_total Denied bpf() calls\n# TYPE elogj_lsm_denied_total counter\nelogj_lsm_denied_total 0\n");
+
+        let config = MetricsConfig { listen: Some(String::from("127.0.0.1:0")) };
+        let addr = serve(&config, move || text.clone()).unwrap().unwrap();
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(&addr).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut res = String::new();
+            stream.read_to_string(&mut res).unwrap();
+            res
+        };
+        let res = get("/metrics");
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(res.contains("Content-Type: text/plain; version=0.0.4"));
+        assert!(res.ends_with("elogj_lsm_denied_total 0\n"));
+        assert!(get("/").starts_with("HTTP/1.1 404 "));
+        assert_eq!(serve(&MetricsConfig::default(), String::new).unwrap(), None);
+
+        assert_eq!(listen_addr("9469"), "127.0.0.1:9469");
+        assert_eq!(listen_addr(":9469"), "127.0.0.1:9469");
+        assert_eq!(listen_addr("0.0.0.0:9469"), "0.0.0.0:9469");
+        let local = serve(&MetricsConfig { listen: Some(String::from(":0")) }, String::new).unwrap().unwrap();
+        assert!(local.starts_with("127.0.0.1:"));
+    }
+}
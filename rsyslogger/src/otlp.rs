@@ -0,0 +1,573 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use flate2::{write::GzEncoder, Compression};
+use opentelemetry_proto::tonic::collector::logs::v1::{logs_service_client::LogsServiceClient, ExportLogsServiceRequest};
+use opentelemetry_proto::tonic::collector::metrics::v1::{metrics_service_client::MetricsServiceClient, ExportMetricsServiceRequest};
+use opentelemetry_proto::tonic::common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue};
+use opentelemetry_proto::tonic::logs::v1::{LogRecord, ResourceLogs, ScopeLogs, SeverityNumber};
+use opentelemetry_proto::tonic::metrics::v1::{metric, number_data_point, AggregationTemporality, Metric, NumberDataPoint,
+    ResourceMetrics, ScopeMetrics, Sum};
+use opentelemetry_proto::tonic::resource::v1::Resource;
+use prost::Message as _;
+use serde::{Serialize, Deserialize};
+use tonic::codec::CompressionEncoding;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use crate::sink::{Message, Severity, Sink};
+use crate::syslog::hostname;
+
+/** OpenTelemetry sink (OTLP):
+ * Events are exported as log records (batched), with network attributes
+ * from the semantic conventions (source.address, destination.port,
+ * network.transport, ...) and elogj.* attributes for the other fields.
+ * Cumulative counters are exported every `metrics_interval` seconds, they
+ * count every event, including the ones the sink filter leaves out:
+ *
+ *  elogj.drops       (elogj.direction, elogj.reason)
+ *  elogj.detections  (elogj.detection)
+ *
+ *  protocol: http (http/protobuf, /v1/logs and /v1/metrics) | grpc (tonic, https:// for TLS)
+ *  compression: none | gzip
+ *
+ * Retryable failures (unavailable, throttled, timeouts) are retried up to
+ * `retries` attempts, `backoff_ms` doubled after each one.
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OtlpConfig {
+    pub endpoint: Option<String>,   // default: http://localhost:4318 (http), http://localhost:4317 (grpc)
+    pub protocol: OtlpProtocol,
+    pub compression: OtlpCompression,
+    pub service_name: String,
+    pub batch: usize,
+    pub metrics_interval: u64,      // seconds, 0: no metrics
+    pub timeout: u64,               // seconds
+    pub retries: u32,               // attempts per export
+    pub backoff_ms: u64,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        OtlpConfig {
+            endpoint: None,
+            protocol: OtlpProtocol::Http,
+            compression: OtlpCompression::None,
+            service_name: String::from("elogj"),
+            batch: 512,
+            metrics_interval: 10,
+            timeout: 10,
+            retries: 3,
+            backoff_ms: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtlpProtocol {
+    Http,
+    Grpc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OtlpCompression {
+    None,
+    Gzip,
+}
+
+impl OtlpConfig {
+    pub fn endpoint(&self) -> String {
+        match (&self.endpoint, self.protocol) {
+            (Some(endpoint), _) => endpoint.trim_end_matches('/').to_string(),
+            (None, OtlpProtocol::Http) => String::from("http://localhost:4318"),
+            (None, OtlpProtocol::Grpc) => String::from("http://localhost:4317"),
+        }
+    }
+}
+
+fn string_value(v: &str) -> Option<AnyValue> {
+    Some(AnyValue { value: Some(any_value::Value::StringValue(v.to_string())) })
+}
+
+fn key_value(key: &str, value: Option<AnyValue>) -> KeyValue {
+    KeyValue { key: key.to_string(), value }
+}
+
+// Event field --> attribute name (semantic conventions for network fields)
+fn attribute(name: &str) -> String {
+    match name {
+        "SRC" => String::from("source.address"),
+        "SRC_PORT" => String::from("source.port"),
+        "DST" => String::from("destination.address"),
+        "DST_PORT" => String::from("destination.port"),
+        "PROTOCOL" => String::from("network.transport"),
+        _ => format!("elogj.{}", name.to_lowercase()),
+    }
+}
+
+const INT_FIELDS: [&str; 4] = ["SRC_PORT", "DST_PORT", "RULE_ID", "SIGNATURE_ID"];
+
+fn severity_number(severity: Severity) -> SeverityNumber {
+    match severity {
+        Severity::Info => SeverityNumber::Info,
+        Severity::Notice => SeverityNumber::Info2,
+        Severity::Warning => SeverityNumber::Warn,
+        Severity::Critical => SeverityNumber::Error,
+    }
+}
+
+fn severity_text(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "INFO",
+        Severity::Notice => "NOTICE",
+        Severity::Warning => "WARN",
+        Severity::Critical => "CRITICAL",
+    }
+}
+
+fn now_ns() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64
+}
+
+fn log_record(msg: &Message) -> LogRecord {
+    let mut attributes = vec![key_value("network.type", string_value("ipv4"))];
+    for (name, value) in &msg.fields {
+        let value = match value.parse::<i64>() {
+            Ok(i) if INT_FIELDS.contains(name) => Some(AnyValue { value: Some(any_value::Value::IntValue(i)) }),
+            _ => string_value(value),
+        };
+        attributes.push(key_value(&attribute(name), value));
+    }
+    LogRecord {
+        time_unix_nano: msg.time_ns,
+        observed_time_unix_nano: now_ns(),
+        severity_number: severity_number(msg.info.severity) as i32,
+        severity_text: String::from(severity_text(msg.info.severity)),
+        body: string_value(&msg.text),
+        attributes,
+        ..Default::default()
+    }
+}
+
+// Cumulative counters of the events seen by the sink
+#[derive(Default)]
+struct Counters {
+    drops: BTreeMap<(String, String), u64>,    // (direction, reason)
+    detections: BTreeMap<String, u64>,
+}
+
+impl Counters {
+    fn add(&mut self, msg: &Message) {
+        let field = |name: &str| msg.fields.iter().find(|(n, _)| *n == name).map(|(_, v)| v.as_str());
+        if msg.info.dropped {
+            let key = (field("DIRECTION").unwrap_or("-").to_lowercase(), field("REASON").unwrap_or("-").to_string());
+            *self.drops.entry(key).or_default() += 1;
+        }
+        for detection in field("DETECTIONS").unwrap_or_default().split(',').filter(|d| !d.is_empty()) {
+            *self.detections.entry(detection.to_string()).or_default() += 1;
+        }
+    }
+}
+
+fn scope() -> Option<InstrumentationScope> {
+    Some(InstrumentationScope {
+        name: String::from("elogj"),
+        version: String::from(env!("CARGO_PKG_VERSION")),
+        ..Default::default()
+    })
+}
+
+pub struct OtlpSink {
+    config: OtlpConfig,
+    transport: Transport,
+    resource: Resource,
+    logs: Vec<LogRecord>,
+    counters: Counters,
+    start_ns: u64,
+    metrics_at: Instant,
+}
+
+impl OtlpSink {
+    pub fn new(config: &OtlpConfig) -> Result<OtlpSink, String> {
+        let transport = Transport::new(config)?;
+        let resource = Resource {
+            attributes: vec![key_value("service.name", string_value(&config.service_name)),
+                key_value("host.name", string_value(&hostname()))],
+            ..Default::default()
+        };
+        Ok(OtlpSink {
+            config: config.clone(),
+            transport,
+            resource,
+            logs: Vec::new(),
+            counters: Counters::default(),
+            start_ns: now_ns(),
+            metrics_at: Instant::now(),
+        })
+    }
+
+    // Export with retries (retryable failures only), backoff_ms doubled after each attempt
+    fn send(&mut self, request: &Request) -> Result<(), String> {
+        let mut backoff = Duration::from_millis(self.config.backoff_ms);
+        let mut attempt = 1;
+        loop {
+            match self.transport.export(request) {
+                Ok(()) => return Ok(()),
+                Err(e) if e.retryable && attempt < self.config.retries.max(1) => {
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                },
+                Err(e) => return Err(format!("{} ({} attempt(s))", e.message, attempt)),
+            }
+        }
+    }
+
+    fn export_logs(&mut self) -> Result<(), String> {
+        if self.logs.is_empty() {
+            return Ok(());
+        }
+        let count = self.logs.len();
+        let request = Request::Logs(ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                resource: Some(self.resource.clone()),
+                scope_logs: vec![ScopeLogs { scope: scope(), log_records: std::mem::take(&mut self.logs), ..Default::default() }],
+                ..Default::default()
+            }],
+        });
+        self.send(&request).map_err(|e| format!("{} log record(s): {}", count, e))
+    }
+
+    fn export_metrics(&mut self) -> Result<(), String> {
+        self.metrics_at = Instant::now();
+        let now = now_ns();
+        let point = |attributes: &[(&str, &str)], value: u64| NumberDataPoint {
+            attributes: attributes.iter().map(|(key, v)| key_value(key, string_value(v))).collect(),
+            start_time_unix_nano: self.start_ns,
+            time_unix_nano: now,
+            value: Some(number_data_point::Value::AsInt(value as i64)),
+            ..Default::default()
+        };
+        let sum = |name: &str, description: &str, unit: &str, data_points: Vec<NumberDataPoint>| Metric {
+            name: name.to_string(),
+            description: description.to_string(),
+            unit: unit.to_string(),
+            data: Some(metric::Data::Sum(Sum {
+                data_points,
+                aggregation_temporality: AggregationTemporality::Cumulative as i32,
+                is_monotonic: true,
+            })),
+            ..Default::default()
+        };
+        let drops = self.counters.drops.iter()
+            .map(|((direction, reason), n)| point(&[("elogj.direction", direction), ("elogj.reason", reason)], *n))
+            .collect();
+        let detections = self.counters.detections.iter()
+            .map(|(detection, n)| point(&[("elogj.detection", detection)], *n))
+            .collect();
+        let request = Request::Metrics(ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(self.resource.clone()),
+                scope_metrics: vec![ScopeMetrics {
+                    scope: scope(),
+                    metrics: vec![sum("elogj.drops", "Dropped packets", "{packet}", drops),
+                        sum("elogj.detections", "Detections in events", "{detection}", detections)],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        });
+        self.send(&request).map_err(|e| format!("metrics: {}", e))
+    }
+}
+
+impl Sink for OtlpSink {
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        self.counters.add(msg);
+        self.logs.push(log_record(msg));
+        if self.logs.len() >= self.config.batch.max(1) {
+            return self.export_logs();
+        }
+        Ok(())
+    }
+
+    // Events filtered out of the logs still update the counters
+    fn count(&mut self, msg: &Message) {
+        self.counters.add(msg);
+    }
+
+    fn counts_all(&self) -> bool {
+        true
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        let logs = self.export_logs();
+        if self.config.metrics_interval != 0 && self.metrics_at.elapsed() >= Duration::from_secs(self.config.metrics_interval) {
+            self.export_metrics()?;
+        }
+        logs
+    }
+}
+
+enum Request {
+    Logs(ExportLogsServiceRequest),
+    Metrics(ExportMetricsServiceRequest),
+}
+
+impl Request {
+    fn http_path(&self) -> &'static str {
+        match self {
+            Request::Logs(_) => "/v1/logs",
+            Request::Metrics(_) => "/v1/metrics",
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Request::Logs(request) => request.encode_to_vec(),
+            Request::Metrics(request) => request.encode_to_vec(),
+        }
+    }
+}
+
+// Failed export, retryable per the OTLP spec (throttling, unavailable, timeouts, connection errors)
+struct ExportError {
+    retryable: bool,
+    message: String,
+}
+
+impl From<ureq::Error> for ExportError {
+    fn from(e: ureq::Error) -> Self {
+        let retryable = match &e {
+            ureq::Error::Status(code, _) => matches!(code, 429 | 502 | 503 | 504),
+            ureq::Error::Transport(_) => true,
+        };
+        ExportError { retryable, message: e.to_string() }
+    }
+}
+
+impl From<tonic::Status> for ExportError {
+    fn from(status: tonic::Status) -> Self {
+        use tonic::Code;
+        let retryable = matches!(status.code(), Code::Cancelled | Code::DeadlineExceeded | Code::ResourceExhausted
+            | Code::Aborted | Code::OutOfRange | Code::Unavailable | Code::DataLoss);
+        ExportError { retryable, message: format!("grpc {:?}: {}", status.code(), status.message()) }
+    }
+}
+
+// Runtime of the gRPC clients, shared by the otlp sinks (their threads block on it)
+fn runtime() -> Result<&'static tokio::runtime::Runtime, String> {
+    static RUNTIME: OnceLock<Result<tokio::runtime::Runtime, String>> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread().worker_threads(1).thread_name("elogj-otlp").enable_all().build()
+            .map_err(|e| e.to_string())
+    }).as_ref().map_err(|e| format!("otlp: {}", e))
+}
+
+enum Transport {
+    Http { agent: ureq::Agent, endpoint: String, gzip: bool },
+    Grpc(Box<Grpc>),
+}
+
+struct Grpc {
+    logs: LogsServiceClient<Channel>,
+    metrics: MetricsServiceClient<Channel>,
+}
+
+impl Transport {
+    fn new(config: &OtlpConfig) -> Result<Transport, String> {
+        let endpoint = config.endpoint();
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Err(format!("otlp: invalid endpoint `{}`", endpoint));
+        }
+        let timeout = Duration::from_secs(config.timeout.max(1));
+        let gzip = config.compression == OtlpCompression::Gzip;
+        match config.protocol {
+            OtlpProtocol::Http => Ok(Transport::Http { agent: ureq::AgentBuilder::new().timeout(timeout).build(), endpoint, gzip }),
+            OtlpProtocol::Grpc => {
+                let mut channel = Endpoint::from_shared(endpoint.clone())
+                    .map_err(|e| format!("otlp: invalid endpoint `{}`: {}", endpoint, e))?
+                    .timeout(timeout)
+                    .connect_timeout(timeout);
+                if endpoint.starts_with("https://") {
+                    channel = channel.tls_config(ClientTlsConfig::new().with_native_roots())
+                        .map_err(|e| format!("otlp: {}: {}", endpoint, e))?;
+                }
+                let channel = {
+                    let _rt = runtime()?.enter();   // connected on the first export
+                    channel.connect_lazy()
+                };
+                let (mut logs, mut metrics) = (LogsServiceClient::new(channel.clone()), MetricsServiceClient::new(channel));
+                if gzip {
+                    logs = logs.send_compressed(CompressionEncoding::Gzip);
+                    metrics = metrics.send_compressed(CompressionEncoding::Gzip);
+                }
+                Ok(Transport::Grpc(Box::new(Grpc { logs, metrics })))
+            },
+        }
+    }
+
+    fn export(&mut self, request: &Request) -> Result<(), ExportError> {
+        match self {
+            Transport::Http { agent, endpoint, gzip } => {
+                let post = agent.post(&format!("{}{}", endpoint, request.http_path()))
+                    .set("Content-Type", "application/x-protobuf");
+                let res = match gzip {
+                    true => post.set("Content-Encoding", "gzip").send_bytes(&gzip_body(&request.encode())),
+                    false => post.send_bytes(&request.encode()),
+                };
+                res.map(|_| ()).map_err(ExportError::from)
+            },
+            Transport::Grpc(grpc) => {
+                let runtime = runtime().map_err(|message| ExportError { retryable: false, message })?;
+                let res = match request {
+                    Request::Logs(request) => runtime.block_on(grpc.logs.export(request.clone())).map(|_| ()),
+                    Request::Metrics(request) => runtime.block_on(grpc.metrics.export(request.clone())).map(|_| ()),
+                };
+                res.map_err(ExportError::from)
+            },
+        }
+    }
+}
+
+fn gzip_body(body: &[u8]) -> Vec<u8> {
+    let mut gz = GzEncoder::new(Vec::with_capacity(body.len() / 2), Compression::default());
+    let _ = gz.write_all(body);     // in memory
+    gz.finish().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::otlp::{OtlpCompression, OtlpConfig, OtlpProtocol, OtlpSink, log_record};
+    use crate::sink::{Message, Sink};
+    use crate::sink::test_util::http_server;
+    use flate2::read::GzDecoder;
+    use opentelemetry_proto::tonic::collector::logs::v1::logs_service_server::{LogsService, LogsServiceServer};
+    use opentelemetry_proto::tonic::collector::logs::v1::{ExportLogsServiceRequest, ExportLogsServiceResponse};
+    use opentelemetry_proto::tonic::collector::metrics::v1::ExportMetricsServiceRequest;
+    use opentelemetry_proto::tonic::common::v1::any_value::Value;
+    use opentelemetry_proto::tonic::metrics::v1::metric::Data;
+    use prost::Message as _;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::mpsc::{channel, Sender};
+    use std::sync::Mutex;
+    use tonic::codec::CompressionEncoding;
+
+    #[test]
+    fn log_record_test() {
+        let record = log_record(&Message::test_drop("a --> b - DROP"));
+        assert_eq!((record.time_unix_nano, record.severity_number, record.severity_text.as_str()),
+            (1_792_397_350_123_000_000, 17, "CRITICAL"));
+        assert_eq!(record.body.unwrap().value, Some(Value::StringValue(String::from("a --> b - DROP"))));
+        let attr = |key: &str| record.attributes.iter().find(|kv| kv.key == key).unwrap().value.clone().unwrap().value.unwrap();
+        assert_eq!(attr("source.address"), Value::StringValue(String::from("10.0.0.1")));
+        assert_eq!(attr("source.port"), Value::IntValue(40000));
+        assert_eq!(attr("network.transport"), Value::StringValue(String::from("tcp")));
+        assert_eq!(attr("elogj.verdict"), Value::StringValue(String::from("DROP")));
+    }
+
+    // Collector stand-in (http/protobuf), the first request is answered with 503
+    #[test]
+    fn http_test() {
+        let (addr, rx) = http_server(|n| match n {
+            0 => ("503 Service Unavailable", String::new()),
+            _ => ("200 OK", String::new()),
+        });
+        let config = OtlpConfig {
+            endpoint: Some(format!("http://{}", addr)),
+            protocol: OtlpProtocol::Http,
+            compression: OtlpCompression::Gzip,
+            batch: 2,
+            backoff_ms: 1,
+            ..OtlpConfig::default()
+        };
+
+        let mut sink = OtlpSink::new(&config).unwrap();
+        sink.write(&Message::test_drop("first")).unwrap();
+        sink.write(&Message::test_drop("second")).unwrap();    // batch full, retried after the 503
+        let gunzip = |body: &[u8]| {
+            let mut raw = Vec::new();
+            GzDecoder::new(body).read_to_end(&mut raw).unwrap();
+            raw
+        };
+        let first = rx.recv().unwrap();
+        let retried = rx.recv().unwrap();
+        assert_eq!(first.body, retried.body);
+        assert!(retried.line.starts_with("POST /v1/logs "));
+        assert_eq!(retried.headers["content-type"], "application/x-protobuf");
+        assert_eq!(retried.headers["content-encoding"], "gzip");
+        let request = ExportLogsServiceRequest::decode(&gunzip(&retried.body)[..]).unwrap();
+        let records = &request.resource_logs[0].scope_logs[0].log_records;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].body.as_ref().unwrap().value, Some(Value::StringValue(String::from("second"))));
+
+        // Filtered out events are counted too
+        sink.count(&Message::test_drop("third"));
+        sink.export_metrics().unwrap();
+        let metrics = rx.recv().unwrap();
+        assert!(metrics.line.starts_with("POST /v1/metrics "));
+        let request = ExportMetricsServiceRequest::decode(&gunzip(&metrics.body)[..]).unwrap();
+        let drops = &request.resource_metrics[0].scope_metrics[0].metrics[0];
+        assert_eq!(drops.name, "elogj.drops");
+        match &drops.data {
+            Some(Data::Sum(sum)) => {
+                assert_eq!(sum.data_points.len(), 1);
+                assert_eq!(sum.data_points[0].attributes[1].value.clone().unwrap().value, Some(Value::StringValue(String::from("jndi_request"))));
+                assert_eq!(sum.data_points[0].value, Some(opentelemetry_proto::tonic::metrics::v1::number_data_point::Value::AsInt(3)));
+            },
+            _ => panic!("sum expected"),
+        }
+    }
+
+    // Collector stand-in (tonic), the first export is answered with UNAVAILABLE
+    struct Collector {
+        calls: AtomicU32,
+        tx: Mutex<Sender<ExportLogsServiceRequest>>,
+    }
+
+    #[tonic::async_trait]
+    impl LogsService for Collector {
+        async fn export(&self, request: tonic::Request<ExportLogsServiceRequest>)
+            -> Result<tonic::Response<ExportLogsServiceResponse>, tonic::Status> {
+            if self.calls.fetch_add(1, Ordering::Relaxed) == 0 {
+                return Err(tonic::Status::unavailable("starting"));
+            }
+            self.tx.lock().unwrap().send(request.into_inner()).unwrap();
+            Ok(tonic::Response::new(ExportLogsServiceResponse::default()))
+        }
+    }
+
+    #[test]
+    fn grpc_test() {
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build().unwrap();
+        let listener = runtime.block_on(tokio::net::TcpListener::bind("127.0.0.1:0")).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = channel::<ExportLogsServiceRequest>();
+        let collector = Collector { calls: AtomicU32::new(0), tx: Mutex::new(tx) };
+        let service = LogsServiceServer::new(collector).accept_compressed(CompressionEncoding::Gzip);
+        let incoming = tonic::transport::server::TcpIncoming::from_listener(listener, true, None).unwrap();
+        runtime.spawn(tonic::transport::Server::builder().add_service(service).serve_with_incoming(incoming));
+
+        let config = OtlpConfig {
+            endpoint: Some(format!("http://{}", addr)),
+            protocol: OtlpProtocol::Grpc,
+            compression: OtlpCompression::Gzip,
+            backoff_ms: 1,
+            ..OtlpConfig::default()
+        };
+        let mut sink = OtlpSink::new(&config).unwrap();
+        sink.write(&Message::test_drop("a --> b - DROP")).unwrap();
+        sink.flush().unwrap();      // retried after UNAVAILABLE
+        let request = rx.recv().unwrap();
+        assert_eq!(request.resource_logs[0].scope_logs[0].log_records.len(), 1);
+
+        sink.write(&Message::test_drop("c --> d - DROP")).unwrap();
+        sink.flush().unwrap();
+        assert_eq!(rx.recv().unwrap().resource_logs[0].scope_logs[0].log_records.len(), 1);
+        assert!(OtlpSink::new(&OtlpConfig { endpoint: Some(String::from("collector:4317")), ..config }).is_err());
+    }
+}
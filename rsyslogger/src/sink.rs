@@ -0,0 +1,590 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use log::{error, warn};
+use serde::{Serialize, Deserialize};
+use crate::dropin::RsyslogConfig;
+use crate::elastic::{ElasticConfig, ElasticSink};
+use crate::file::{FileConfig, RotatingFile};
+use crate::journald::{JournaldConfig, JournaldSink};
+use crate::metrics::Metrics;
+use crate::otlp::{OtlpConfig, OtlpSink};
+use crate::socket::{SocketConfig, SocketSink};
+use crate::syslog::{SyslogConfig, SyslogSink};
+use crate::webhook::{WebhookConfig, WebhookSink};
+
+/** Output sinks (`sinks:`):
+ * Every event is offered to each sink, a sink only receives the events
+ * its filter accepts. Each sink has its own bounded queue and writer thread:
+ * a slow (or down) sink drops its own messages, never blocks the perf
+ * readers nor the other sinks.
+ *
+ *  - type: console | file | syslog | journald | otlp | webhook | elastic | socket (+ the options of the sink type)
+ *    min_severity: info | notice | warning | critical   (webhook: warning, others: info)
+ *    drops_only: true       (dropped events only)
+ *    direction: inbound | outbound
+ *    queue: 4096
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    #[serde(flatten)]
+    pub filter: Filter,
+    #[serde(default = "default_queue")]
+    pub queue: usize,
+}
+
+fn default_queue() -> usize {
+    4096
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkKind {
+    Console,
+    File(FileConfig),
+    Syslog(SyslogConfig),
+    Journald(JournaldConfig),
+    Otlp(OtlpConfig),
+    Webhook(WebhookConfig),
+    Elastic(ElasticConfig),
+    Socket(SocketConfig),
+}
+
+// Event severity: pass => info, whitelist override => notice, alert => warning, drop => critical
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Notice,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Filter {
+    pub min_severity: Option<Severity>,   // default: SinkKind::min_severity
+    pub drops_only: bool,
+    pub direction: Option<Direction>,
+}
+
+// What the filters see of an event
+#[derive(Debug, Clone, Copy)]
+pub struct EventInfo {
+    pub severity: Severity,
+    pub dropped: bool,
+    pub direction: Direction,
+}
+
+// Formatted event (text), its structured fields, ex: ("VERDICT", "DROP"), and its JSON record
+// (only built when a sink uses it, see SinkKind::uses_json)
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub text: String,
+    pub info: EventInfo,
+    pub fields: Vec<(&'static str, String)>,
+    pub json: String,
+    pub time_ns: u64,   // event time (since epoch)
+}
+
+impl Filter {
+    pub fn matches(&self, event: &EventInfo) -> bool {
+        event.severity >= self.min_severity.unwrap_or_default()
+            && (!self.drops_only || event.dropped)
+            && (self.direction.is_none() || self.direction == Some(event.direction))
+    }
+}
+
+impl SinkKind {
+    // Default min_severity: the webhook only gets alerts and drops
+    pub fn min_severity(&self) -> Severity {
+        match self {
+            SinkKind::Webhook(_) => Severity::Warning,
+            _ => Severity::Info,
+        }
+    }
+
+    // Sinks sending Message::json
+    pub fn uses_json(&self) -> bool {
+        matches!(self, SinkKind::Webhook(_) | SinkKind::Socket(_))
+    }
+}
+
+impl SinkConfig {
+    pub fn name(&self) -> String {
+        match &self.kind {
+            SinkKind::Console => String::from("console"),
+            SinkKind::File(file) => format!("file {}/{}", file.dir, file.name),
+            SinkKind::Syslog(syslog) => format!("syslog {:?} {}", syslog.transport, syslog.address),
+            SinkKind::Journald(journald) => format!("journald {}", journald.socket),
+            SinkKind::Otlp(otlp) => format!("otlp {}", otlp.endpoint()),
+            SinkKind::Webhook(webhook) => format!("webhook {}", webhook.url),
+            SinkKind::Elastic(elastic) => format!("elastic {}", elastic.endpoint.as_deref().unwrap_or(&elastic.dir)),
+            SinkKind::Socket(socket) => format!("socket {}", socket.path),
+        }
+    }
+
+    // Sinks of the former `log_type` (used when `sinks` isn't set)
+    pub fn from_log_type(log_type: &str, syslog: &SyslogConfig, file: &FileConfig, rsyslog: &RsyslogConfig)
+        -> Result<Vec<SinkConfig>, String> {
+        let kind = match log_type {
+            "local" => SinkKind::Console,
+            "file" => SinkKind::File(file.clone()),
+            "manager" => {
+                rsyslog.validate()?;    // forwarded by the drop-in
+                SinkKind::Syslog(syslog.clone())
+            },
+            _ => return Err(format!("unknown log_type `{}` (file | local | manager)", log_type)),
+        };
+        Ok(vec![SinkConfig { kind, filter: Filter::default(), queue: default_queue() }])
+    }
+
+    // The rsyslog drop-in forwards the messages of the first syslog sink (tag)
+    pub fn dropin_tag<'a>(sinks: &'a [SinkConfig], rsyslog: &RsyslogConfig) -> Option<&'a str> {
+        rsyslog.forward.as_ref()?;
+        sinks.iter().find_map(|sink| match &sink.kind {
+            SinkKind::Syslog(syslog) => Some(syslog.tag.as_str()),
+            _ => None,
+        })
+    }
+}
+
+// Blocking writer, runs on the sink thread
+pub trait Sink: Send + 'static {
+    fn write(&mut self, msg: &Message) -> Result<(), String>;
+
+    // Called every FLUSH_INTERVAL and when the queue closes (batching sinks)
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    // Sinks that count every event (ex: OTLP counters) also get the ones their filter rejects, through count
+    fn counts_all(&self) -> bool {
+        false
+    }
+
+    fn count(&mut self, _msg: &Message) {}
+}
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Console;
+
+impl Sink for Console {
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        writeln!(std::io::stdout().lock(), "{}", msg.text).map_err(|e| e.to_string())
+    }
+}
+
+// Counters of a sink (queue full drops, write errors, queued messages)
+#[derive(Default)]
+struct Stats {
+    dropped: AtomicU64,
+    errors: AtomicU64,
+    queued: AtomicU64,
+}
+
+// Sink queue entry: (message , accepted by the filter), None closes the queue (Worker::shutdown)
+type Queued = Option<(Arc<Message>, bool)>;
+
+// Queue of a sink thread, cloneable (shared by the event readers)
+#[derive(Clone)]
+pub struct Worker {
+    name: Arc<str>,
+    filter: Filter,
+    tx: SyncSender<Queued>,
+    capacity: usize,
+    counts_all: bool,
+    json: bool,
+    stats: Arc<Stats>,
+    thread: Arc<Mutex<Option<JoinHandle<()>>>>,    // taken by the first shutdown
+}
+
+impl Worker {
+    pub fn spawn(config: &SinkConfig) -> Result<Worker, String> {
+        let name: Arc<str> = Arc::from(config.name());
+        let sink: Box<dyn Sink> = match &config.kind {
+            SinkKind::Console => Box::new(Console),
+            SinkKind::File(file) => Box::new(RotatingFile::open(file)?),
+            SinkKind::Syslog(syslog) => Box::new(SyslogSink::new(syslog)?),
+            SinkKind::Journald(journald) => Box::new(JournaldSink::new(journald)?),
+            SinkKind::Otlp(otlp) => Box::new(OtlpSink::new(otlp)?),
+            SinkKind::Webhook(webhook) => Box::new(WebhookSink::new(webhook)?),
+            SinkKind::Elastic(elastic) => Box::new(ElasticSink::new(elastic)?),
+            SinkKind::Socket(socket) => Box::new(SocketSink::new(socket)?),
+        };
+        let counts_all = sink.counts_all();
+        let capacity = config.queue.max(1);
+        let (tx, rx) = sync_channel::<Queued>(capacity);
+        let stats = Arc::new(Stats::default());
+        let (thread_name, thread_stats) = (name.clone(), stats.clone());
+        let thread = thread::Builder::new()
+            .name(String::from("elogj-sink"))
+            .spawn(move || write_loop(thread_name, sink, rx, thread_stats, FLUSH_INTERVAL))
+            .map_err(|e| format!("{}: writer thread: {}", name, e))?;
+        let filter = Filter { min_severity: config.filter.min_severity.or(Some(config.kind.min_severity())), ..config.filter.clone() };
+        Ok(Worker { name, filter, tx, capacity, counts_all, json: config.kind.uses_json(), stats,
+            thread: Arc::new(Mutex::new(Some(thread))) })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Queues msg if the filter accepts the event (or the sink counts all), never blocks (dropped if the queue is full)
+    pub fn log(&self, msg: &Arc<Message>) {
+        let accepted = self.filter.matches(&msg.info);
+        if !accepted && !self.counts_all {
+            return;
+        }
+        self.stats.queued.fetch_add(1, Ordering::Relaxed);
+        if let Err(e) = self.tx.try_send(Some((msg.clone(), accepted))) {
+            self.stats.queued.fetch_sub(1, Ordering::Relaxed);
+            if let TrySendError::Full(_) = e {
+                self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.stats.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.stats.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn queued(&self) -> u64 {
+        self.stats.queued.load(Ordering::Relaxed)
+    }
+
+    // Closes the queue behind the messages already queued (waits for room) and waits for the final flush,
+    // clones still held by the event readers don't keep the thread alive
+    pub fn shutdown(&self) {
+        let Some(thread) = self.thread.lock().ok().and_then(|mut thread| thread.take()) else {
+            return;
+        };
+        let _ = self.tx.send(None);     // Err: the thread is already gone
+        if thread.join().is_err() {
+            error!("{}: writer thread panicked", self.name);
+        }
+    }
+}
+
+// Writes the queued messages, flushes every `interval` (FLUSH_INTERVAL) and once the queue closes
+fn write_loop(name: Arc<str>, mut sink: Box<dyn Sink>, rx: Receiver<Queued>, stats: Arc<Stats>, interval: Duration) {
+    let report = |res: Result<(), String>| {
+        if let Err(e) = res {
+            stats.errors.fetch_add(1, Ordering::Relaxed);
+            warn!("{}: message(s) dropped ({})", name, e);
+        }
+    };
+    let mut last_flush = Instant::now();
+    loop {
+        let closed = match rx.recv_timeout(interval.saturating_sub(last_flush.elapsed())) {
+            Ok(Some((msg, accepted))) => {
+                stats.queued.fetch_sub(1, Ordering::Relaxed);
+                match accepted {
+                    true => report(sink.write(&msg)),
+                    false => sink.count(&msg),
+                }
+                false
+            },
+            Err(RecvTimeoutError::Timeout) => false,
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => true,
+        };
+        // Flushed every interval, even if the queue never stays empty that long
+        if closed || last_flush.elapsed() >= interval {
+            report(sink.flush());
+            last_flush = Instant::now();
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+// Fan-out to all the sinks
+#[derive(Clone)]
+pub struct Sinks {
+    workers: Vec<Worker>,
+}
+
+impl Sinks {
+    pub fn spawn(configs: &[SinkConfig]) -> Result<Sinks, String> {
+        if configs.is_empty() {
+            return Err(String::from("no output sink configured"));
+        }
+        let workers = configs.iter().map(Worker::spawn).collect::<Result<Vec<_>, _>>()?;
+        Ok(Sinks { workers })
+    }
+
+    pub fn log(&self, msg: Message) {
+        let msg = Arc::new(msg);
+        for worker in &self.workers {
+            worker.log(&msg);
+        }
+    }
+
+    pub fn workers(&self) -> &[Worker] {
+        &self.workers
+    }
+
+    // Flushes and stops every sink thread (see Worker::shutdown), later messages are discarded
+    pub fn shutdown(&self) {
+        for worker in &self.workers {
+            worker.shutdown();
+        }
+    }
+
+    // Whether the messages need their JSON record
+    pub fn uses_json(&self) -> bool {
+        self.workers.iter().any(|w| w.json)
+    }
+
+    // Per sink counters and queue depth
+    pub fn metrics(&self, metrics: &mut Metrics) {
+        for worker in &self.workers {
+            let labels = [("sink", worker.name())];
+            metrics.counter("elogj_sink_dropped_total", "Messages dropped, sink queue full", &labels, worker.dropped());
+            metrics.counter("elogj_sink_errors_total", "Sink write/flush errors", &labels, worker.errors());
+            metrics.gauge("elogj_sink_queue_depth", "Messages waiting in the sink queue", &labels, worker.queued());
+            metrics.gauge("elogj_sink_queue_capacity", "Sink queue capacity", &labels, worker.capacity as u64);
+        }
+    }
+}
+
+// Shared by the sink tests: test messages and an HTTP endpoint stand-in
+#[cfg(test)]
+pub(crate) mod test_util {
+    use crate::sink::{Direction, EventInfo, Message, Severity};
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread;
+
+    impl Message {
+        // Event without fields, critical events are drops
+        pub(crate) fn test(text: &str, severity: Severity, direction: Direction) -> Message {
+            Message {
+                text: String::from(text),
+                info: EventInfo { severity, dropped: severity == Severity::Critical, direction },
+                fields: Vec::new(),
+                json: String::new(),
+                time_ns: 0,
+            }
+        }
+
+        // Inbound JNDI request drop, at 2026-10-19T08:09:10.123Z
+        pub(crate) fn test_drop(text: &str) -> Message {
+            Message {
+                fields: vec![("SRC", String::from("10.0.0.1")), ("DST", String::from("172.17.0.2")),
+                    ("SRC_PORT", String::from("40000")), ("DST_PORT", String::from("8080")),
+                    ("PROTOCOL", String::from("tcp")), ("DIRECTION", String::from("INBOUND")),
+                    ("VERDICT", String::from("DROP")), ("REASON", String::from("jndi_request")),
+                    ("RULE_ID", String::from("3")), ("DETECTIONS", String::from("interpolation,jndi")),
+                    ("FLOW_ID", String::from("0000abcd"))],
+                time_ns: 1_792_397_350_123_000_000,
+                ..Message::test(text, Severity::Critical, Direction::Inbound)
+            }
+        }
+    }
+
+    // Request line, headers (lowercase names) and body of a request to the stand-in
+    pub(crate) struct HttpRequest {
+        pub line: String,
+        pub headers: HashMap<String, String>,
+        pub body: Vec<u8>,
+    }
+
+    // HTTP endpoint stand-in: one request per connection, respond(n) is the (status , JSON body) of the nth one
+    pub(crate) fn http_server(respond: impl Fn(usize) -> (&'static str, String) + Send + 'static)
+        -> (SocketAddr, Receiver<HttpRequest>) {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let (tx, rx) = channel::<HttpRequest>();
+        thread::spawn(move || {
+            for (n, stream) in server.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut headers = HashMap::new();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    let (name, value) = header.split_once(':').unwrap();
+                    headers.insert(name.to_lowercase(), value.trim().to_string());
+                }
+                let len = headers.get("content-length").map_or(0, |l| l.parse().unwrap());
+                let mut body = vec![0u8; len];
+                reader.read_exact(&mut body).unwrap();
+                let (status, response) = respond(n);
+                stream.write_all(format!("HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status, response.len(), response).as_bytes()).unwrap();
+                tx.send(HttpRequest { line, headers, body }).unwrap();
+            }
+        });
+        (addr, rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::Metrics;
+    use crate::sink::{write_loop, Direction, EventInfo, Filter, Message, Queued, Severity, Sink, SinkConfig, SinkKind, Sinks, Stats};
+    use crate::sink::test_util::http_server;
+    use crate::syslog::Transport;
+    use std::net::UdpSocket;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::mpsc::sync_channel;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    // Counts flushes, written messages are pending until the next flush
+    struct FlushCount {
+        flushes: Arc<AtomicU64>,
+        pending: Arc<AtomicU64>,
+    }
+
+    impl Sink for FlushCount {
+        fn write(&mut self, _msg: &Message) -> Result<(), String> {
+            self.pending.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> Result<(), String> {
+            self.flushes.fetch_add(1, Ordering::Relaxed);
+            self.pending.store(0, Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn flush_test() {
+        // Steady traffic (a message every 5ms) still flushes every interval
+        let (flushes, pending) = (Arc::new(AtomicU64::new(0)), Arc::new(AtomicU64::new(0)));
+        let (tx, rx) = sync_channel::<Queued>(16);
+        let sink = Box::new(FlushCount { flushes: flushes.clone(), pending: pending.clone() });
+        let interval = Duration::from_millis(20);
+        let writer = thread::spawn(move || write_loop(Arc::from("test"), sink, rx, Arc::new(Stats::default()), interval));
+        let msg = Arc::new(Message::test("a --> b - PASS", Severity::Info, Direction::Outbound));
+        let start = Instant::now();
+        while start.elapsed() < interval * 10 {
+            tx.send(Some((msg.clone(), true))).unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(flushes.load(Ordering::Relaxed) >= 1);
+        let before = flushes.load(Ordering::Relaxed);
+        tx.send(Some((msg.clone(), true))).unwrap();
+        drop(tx);
+        writer.join().unwrap();
+        assert!(flushes.load(Ordering::Relaxed) > before);     // final flush
+        assert_eq!(pending.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn shutdown_test() {
+        // A batch still buffered is flushed on shutdown, even with Sinks clones left (event readers)
+        let (addr, rx) = http_server(|_| ("200 OK", String::new()));
+        let configs: Vec<SinkConfig> = serde_yaml::from_str(&format!("
+- type: webhook
+  url: http://{}/hook
+  batch: 100
+", addr)).unwrap();
+        let sinks = Sinks::spawn(&configs).unwrap();
+        let reader = sinks.clone();
+        reader.log(Message { json: String::from("{\"id\":1}"), ..Message::test("alert", Severity::Critical, Direction::Outbound) });
+        sinks.shutdown();
+        let request = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(String::from_utf8_lossy(&request.body).ends_with("\"count\":1,\"alerts\":[{\"id\":1}]}"));
+        reader.log(Message::test("late", Severity::Critical, Direction::Outbound));  // discarded
+        sinks.shutdown();
+    }
+
+    #[test]
+    fn filter_test() {
+        let sinks: Vec<SinkConfig> = serde_yaml::from_str("
+- type: console
+- type: syslog
+  transport: udp
+  address: 127.0.0.1:514
+  min_severity: warning
+  drops_only: true
+  direction: inbound
+  queue: 16
+").unwrap();
+        assert!(matches!(sinks[0].kind, SinkKind::Console));
+        assert_eq!(sinks[0].queue, 4096);
+        match &sinks[1].kind {
+            SinkKind::Syslog(syslog) => assert_eq!((syslog.transport, syslog.tag.as_str()), (Transport::Udp, "elogj.info")),
+            _ => panic!("syslog sink expected"),
+        }
+        assert_eq!(sinks[1].queue, 16);
+
+        let mut event = EventInfo { severity: Severity::Critical, dropped: true, direction: Direction::Inbound };
+        assert!(sinks[1].filter.matches(&event));
+        event.direction = Direction::Outbound;
+        assert!(!sinks[1].filter.matches(&event));
+        event = EventInfo { severity: Severity::Warning, dropped: false, direction: Direction::Inbound };
+        assert!(!sinks[1].filter.matches(&event));
+        assert!(Filter::default().matches(&EventInfo { severity: Severity::Info, ..event }));
+        assert!(!Filter { min_severity: Some(Severity::Notice), ..Filter::default() }
+            .matches(&EventInfo { severity: Severity::Info, ..event }));
+    }
+
+    #[test]
+    fn fanout_test() {
+        let inbound = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let all = UdpSocket::bind("127.0.0.1:0").unwrap();
+        for server in [&inbound, &all] {
+            server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        }
+        let sinks: Vec<SinkConfig> = serde_yaml::from_str(&format!("
+- type: syslog
+  transport: udp
+  address: {}
+  direction: inbound
+- type: syslog
+  transport: udp
+  address: {}
+", inbound.local_addr().unwrap(), all.local_addr().unwrap())).unwrap();
+        let sinks = Sinks::spawn(&sinks).unwrap();
+        sinks.log(Message::test("a --> b - PASS", Severity::Info, Direction::Outbound));
+        sinks.log(Message::test("c --> d - DROP", Severity::Critical, Direction::Inbound));
+
+        let mut buf = [0u8; 512];
+        let len = inbound.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).ends_with("c --> d - DROP"));
+        let len = all.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).ends_with("a --> b - PASS"));
+        let len = all.recv(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf[..len]).ends_with("c --> d - DROP"));
+        assert!(sinks.workers().iter().all(|w| w.dropped() == 0 && w.errors() == 0));
+        let mut metrics = Metrics::default();
+        sinks.metrics(&mut metrics);
+        assert!(metrics.render().contains(&format!("elogj_sink_queue_capacity{{sink=\"syslog Udp {}\"}} 4096\n", all.local_addr().unwrap())));
+        let (syslog, file, rsyslog) = Default::default();
+        assert!(SinkConfig::from_log_type("remote", &syslog, &file, &rsyslog).is_err());
+        assert!(SinkConfig::from_log_type("manager", &syslog, &file, &rsyslog).is_err());
+        assert!(matches!(SinkConfig::from_log_type("file", &syslog, &file, &rsyslog).unwrap()[0].kind, SinkKind::File(_)));
+    }
+}
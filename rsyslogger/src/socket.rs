@@ -0,0 +1,408 @@
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+use crate::sink::{Message, Severity, Sink};
+
+/** Event stream socket (socket sink):
+ * Local tools subscribe by connecting to the Unix socket, events are streamed
+ * as JSON lines (the JSON records). A subscriber can send a filter expression
+ * (one line, replaces the previous one, empty: all events), answered with
+ * {"filter": "..."} or {"error": "..."}:
+ *
+ *  verdict=DROP direction=inbound     (terms are and-ed, case insensitive)
+ *  severity>=warning dst_port=80,8080 (a,b: any of)
+ *  detections~jndi reason!=dns_query  (~: contains)
+ *
+ * A subscriber more than `max_pending` bytes behind is sent
+ * {"notice": "too slow, disconnected"} and closed, the other subscribers
+ * and the event readers never wait for it.
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SocketConfig {
+    pub path: String,
+    pub mode: String,
+    pub max_subscribers: usize,
+    pub max_pending: usize,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        SocketConfig {
+            path: String::from("/run/elogj/events.sock"),
+            mode: String::from("0660"),
+            max_subscribers: 16,
+            max_pending: 1024 * 1024,
+        }
+    }
+}
+
+impl SocketConfig {
+    pub fn validate(&self) -> Result<u32, String> {
+        if !Path::new(&self.path).is_absolute() {
+            return Err(format!("socket: path must be absolute (`{}`)", self.path));
+        }
+        match u32::from_str_radix(self.mode.trim_start_matches("0o"), 8) {
+            Ok(mode) if mode & !0o777 == 0 => Ok(mode),
+            _ => Err(format!("socket: invalid mode `{}` (octal, e.g. 0660)", self.mode)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Contains,
+    Ge,
+    Gt,
+    Le,
+    Lt,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    key: String,    // field name (uppercase) or SEVERITY
+    op: Op,
+    values: Vec<String>,
+}
+
+// Filter expression of a subscriber (and-ed terms)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Expr(Vec<Term>);
+
+fn severity(value: &str) -> Option<Severity> {
+    match value.to_lowercase().as_str() {
+        "info" => Some(Severity::Info),
+        "notice" => Some(Severity::Notice),
+        "warning" => Some(Severity::Warning),
+        "critical" => Some(Severity::Critical),
+        _ => None,
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Expr, String> {
+        const OPS: [(&str, Op); 7] = [("!=", Op::Ne), (">=", Op::Ge), ("<=", Op::Le), ("=", Op::Eq), ("~", Op::Contains),
+            (">", Op::Gt), ("<", Op::Lt)];
+        let mut terms: Vec<Term> = Vec::new();
+        for term in s.split_whitespace() {
+            let at = term.find(['!', '=', '~', '<', '>']).ok_or(format!("`{}`: operator expected (= != ~ < <= > >=)", term))?;
+            let (key, rest) = term.split_at(at);
+            let (op, value) = OPS.iter().find_map(|(token, op)| rest.strip_prefix(token).map(|v| (*op, v)))
+                .ok_or(format!("`{}`: unknown operator", term))?;
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(format!("`{}`: invalid field name", term));
+            }
+            let values: Vec<String> = value.split(',').filter(|v| !v.is_empty()).map(|v| v.to_lowercase()).collect();
+            if values.is_empty() {
+                return Err(format!("`{}`: value expected", term));
+            }
+            let key = key.to_uppercase();
+            if key == "SEVERITY" && values.iter().any(|v| severity(v).is_none()) {
+                return Err(format!("`{}`: info | notice | warning | critical expected", term));
+            }
+            terms.push(Term { key, op, values });
+        }
+        Ok(Expr(terms))
+    }
+}
+
+impl Term {
+    fn matches(&self, msg: &Message) -> bool {
+        let value = match self.key.as_str() {
+            "SEVERITY" => {
+                let s = msg.info.severity;
+                let mut values = self.values.iter().filter_map(|v| severity(v));
+                return match self.op {
+                    Op::Eq | Op::Contains => values.any(|v| s == v),
+                    Op::Ne => values.all(|v| s != v),
+                    Op::Ge => values.any(|v| s >= v),
+                    Op::Gt => values.any(|v| s > v),
+                    Op::Le => values.any(|v| s <= v),
+                    Op::Lt => values.any(|v| s < v),
+                };
+            },
+            key => msg.fields.iter().find(|(name, _)| *name == key).map(|(_, v)| v.to_lowercase()),
+        };
+        let Some(value) = value else {
+            return self.op == Op::Ne;  // field not set
+        };
+        let number = |v: &str| v.parse::<i64>().ok();
+        match self.op {
+            Op::Eq => self.values.contains(&value),
+            Op::Ne => self.values.iter().all(|v| *v != value),
+            Op::Contains => self.values.iter().any(|v| value.contains(v.as_str())),
+            op => self.values.iter().any(|v| match (number(&value), number(v)) {
+                (Some(a), Some(b)) => match op {
+                    Op::Ge => a >= b,
+                    Op::Gt => a > b,
+                    Op::Le => a <= b,
+                    _ => a < b,
+                },
+                _ => false,
+            }),
+        }
+    }
+}
+
+impl Expr {
+    pub fn matches(&self, msg: &Message) -> bool {
+        self.0.iter().all(|term| term.matches(msg))
+    }
+}
+
+const MAX_LINE: usize = 4096;
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Subscriber {
+    stream: UnixStream,
+    filter: Expr,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    mid_line: bool,                 // output starts with the rest of a partly written line
+    closing: Option<Instant>,       // too slow, closed once the notice is sent
+}
+
+impl Subscriber {
+    fn new(stream: UnixStream) -> Subscriber {
+        Subscriber { stream, filter: Expr::default(), input: Vec::new(), output: Vec::new(), mid_line: false, closing: None }
+    }
+
+    // Filter lines sent by the subscriber, false once it's gone
+    fn read(&mut self) -> bool {
+        let mut buf = [0u8; 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => return false,
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            }
+        }
+        while let Some(end) = self.input.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.input.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let reply = match line.trim().parse::<Expr>() {
+                Ok(filter) => {
+                    self.filter = filter;
+                    format!("{{\"filter\":{}}}\n", serde_json::to_string(line.trim()).unwrap_or_default())
+                },
+                Err(e) => format!("{{\"error\":{}}}\n", serde_json::to_string(&e).unwrap_or_default()),
+            };
+            self.output.extend_from_slice(reply.as_bytes());
+        }
+        if self.input.len() > MAX_LINE {
+            self.input.clear();
+            self.output.extend_from_slice(b"{\"error\":\"filter too long\"}\n");
+        }
+        true
+    }
+
+    // Sends what the socket takes, false once it's gone (or closed)
+    fn write(&mut self) -> bool {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.mid_line = self.output[n - 1] != b'\n';
+                    self.output.drain(..n);
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return match self.closing {
+                    Some(at) => at.elapsed() < CLOSE_TIMEOUT,
+                    None => true,
+                },
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            }
+        }
+        self.closing.is_none()
+    }
+
+    // false: too slow, the pending events are replaced by the notice
+    fn send(&mut self, line: &[u8], max_pending: usize) -> bool {
+        if self.output.len() + line.len() <= max_pending {
+            self.output.extend_from_slice(line);
+            return true;
+        }
+        let keep = if self.mid_line { self.output.iter().position(|b| *b == b'\n').map_or(0, |p| p + 1) } else { 0 };
+        self.output.truncate(keep);
+        self.output.extend_from_slice(b"{\"notice\":\"too slow, disconnected\"}\n");
+        self.closing = Some(Instant::now());
+        false
+    }
+}
+
+pub struct SocketSink {
+    config: SocketConfig,
+    listener: UnixListener,
+    subscribers: Vec<Subscriber>,
+}
+
+impl SocketSink {
+    pub fn new(config: &SocketConfig) -> Result<SocketSink, String> {
+        let mode = config.validate()?;
+        let path = Path::new(&config.path);
+        if let Some(dir) = path.parent() {
+            fs::DirBuilder::new().recursive(true).mode(0o755).create(dir)
+                .map_err(|e| format!("socket: create {}: {}", dir.display(), e))?;
+        }
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path)   // stale socket
+                .map_err(|e| format!("socket: remove {}: {}", config.path, e))?,
+            Ok(_) => return Err(format!("socket: {} exists and isn't a socket", config.path)),
+            Err(_) => {},
+        }
+        let listener = UnixListener::bind(path).map_err(|e| format!("socket: bind {}: {}", config.path, e))?;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))
+            .and_then(|_| listener.set_nonblocking(true))
+            .map_err(|e| format!("socket: {}: {}", config.path, e))?;
+        Ok(SocketSink { config: config.clone(), listener, subscribers: Vec::new() })
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    // New subscribers, filter updates and pending writes
+    fn poll(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((mut stream, _)) => {
+                    if self.subscribers.len() >= self.config.max_subscribers || stream.set_nonblocking(true).is_err() {
+                        let _ = stream.write_all(b"{\"error\":\"too many subscribers\"}\n");
+                        continue;
+                    }
+                    self.subscribers.push(Subscriber::new(stream));
+                },
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => break,
+            }
+        }
+        self.subscribers.retain_mut(|sub| (sub.closing.is_some() || sub.read()) && sub.write());
+    }
+}
+
+impl Sink for SocketSink {
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        self.poll();
+        let mut line = msg.json.clone().into_bytes();
+        line.push(b'\n');
+        let max_pending = self.config.max_pending;
+        let mut slow = 0;
+        for sub in self.subscribers.iter_mut().filter(|sub| sub.closing.is_none() && sub.filter.matches(msg)) {
+            if !sub.send(&line, max_pending) {
+                slow += 1;
+            }
+        }
+        self.subscribers.retain_mut(|sub| sub.write());
+        if slow != 0 {
+            return Err(format!("{} slow subscriber(s) disconnected", slow));
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.poll();
+        Ok(())
+    }
+}
+
+impl Drop for SocketSink {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.config.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sink::{Direction, Message, Severity, Sink};
+    use crate::socket::{Expr, SocketConfig, SocketSink};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+    use std::time::Duration;
+
+    // Inbound event with its destination port, verdict and JSON record
+    fn event(verdict: &str, severity: Severity, port: u16) -> Message {
+        Message {
+            fields: vec![("DST_PORT", port.to_string()), ("VERDICT", String::from(verdict)),
+                ("DETECTIONS", String::from("interpolation,jndi"))],
+            json: format!("{{\"verdict\":\"{}\",\"dst_port\":{}}}", verdict, port),
+            ..Message::test(&format!("a --> b - {}", verdict), severity, Direction::Inbound)
+        }
+    }
+
+    #[test]
+    fn expr_test() {
+        let drop = event("DROP", Severity::Critical, 8080);
+        let pass = event("PASS", Severity::Info, 80);
+        let matches = |expr: &str, msg: &Message| expr.parse::<Expr>().unwrap().matches(msg);
+        assert!(matches("", &pass));
+        assert!(matches("verdict=drop", &drop) && !matches("verdict=drop", &pass));
+        assert!(matches("severity>=warning detections~JNDI", &drop) && !matches("severity>=warning", &pass));
+        assert!(matches("dst_port=80,443", &pass) && matches("dst_port>1024", &drop) && !matches("dst_port>1024", &pass));
+        assert!(matches("reason!=dns_query", &pass) && !matches("reason=dns_query", &pass));
+        assert!("verdict".parse::<Expr>().is_err());
+        assert!("severity>=high".parse::<Expr>().is_err());
+        assert!("dst-port=1".parse::<Expr>().is_err());
+    }
+
+    #[test]
+    fn stream_test() {
+        let path = std::env::temp_dir().join(format!("elogj-events-{}.sock", std::process::id()));
+        let config = SocketConfig { path: path.display().to_string(), max_pending: 64 * 1024, ..SocketConfig::default() };
+        let mut sink = SocketSink::new(&config).unwrap();
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut reader = BufReader::new(client.try_clone().unwrap());
+        client.write_all(b"verdict=DROP\n").unwrap();
+        thread::sleep(Duration::from_millis(20));
+        sink.flush().unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "{\"filter\":\"verdict=DROP\"}\n");
+
+        sink.write(&event("PASS", Severity::Info, 80)).unwrap();
+        sink.write(&event("DROP", Severity::Critical, 8080)).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+        assert_eq!(line, "{\"verdict\":\"DROP\",\"dst_port\":8080}\n");
+
+        // A subscriber that never reads
+        let slow = UnixStream::connect(&path).unwrap();
+        sink.flush().unwrap();
+        assert_eq!(sink.subscribers(), 2);
+        let mut msg = event("PASS", Severity::Info, 80);   // filtered out by the first subscriber
+        msg.json = format!("{{\"pad\":\"{}\"}}", "x".repeat(1024));
+        let mut sent = 0;
+        while sink.write(&msg).is_ok() {
+            sent += 1;
+            assert!(sent < 10_000);
+        }
+        let reading = thread::spawn(move || {
+            let mut buf = String::new();
+            BufReader::new(slow).read_to_string(&mut buf).unwrap();
+            buf
+        });
+        while sink.subscribers() == 2 {
+            sink.flush().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+        let buf = reading.join().unwrap();
+        assert!(buf.lines().all(|l| l.starts_with('{') && l.ends_with('}')));
+        assert_eq!(buf.lines().last(), Some("{\"notice\":\"too slow, disconnected\"}"));
+
+        drop(sink);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,239 @@
+use std::io::Write;
+use std::net::{TcpStream, UdpSocket};
+use std::os::unix::net::UnixDatagram;
+use std::thread;
+use std::time::Duration;
+use chrono::{DateTime, Local, SecondsFormat};
+use serde::{Serialize, Deserialize};
+use crate::sink::{Message, Sink};
+
+/** Syslog client:
+ * In-process replacement of `logger -i -t elogj.info -p local6.info`
+ * (syslog sink, see sink.rs for queueing).
+ *
+ *  transport: unix (/dev/log, datagram) | udp (host:port) | tcp (host:port)
+ *  format:    rfc3164 (BSD) | rfc5424 (tcp: octet counting framing, RFC 6587)
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SyslogConfig {
+    pub transport: Transport,
+    pub address: String,
+    pub format: SyslogFormat,
+    pub facility: String,
+    pub severity: String,
+    pub tag: String,
+}
+
+impl Default for SyslogConfig {
+    fn default() -> Self {
+        SyslogConfig {
+            transport: Transport::Unix,
+            address: String::from("/dev/log"),
+            format: SyslogFormat::Rfc3164,
+            facility: String::from("local6"),
+            severity: String::from("info"),
+            tag: String::from("elogj.info"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Unix,
+    Udp,
+    Tcp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogFormat {
+    Rfc3164,
+    Rfc5424,
+}
+
+const FACILITIES: [&str; 24] = ["kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron",
+    "authpriv", "ftp", "ntp", "security", "console", "solaris-cron", "local0", "local1", "local2", "local3",
+    "local4", "local5", "local6", "local7"];
+const SEVERITIES: [&str; 8] = ["emerg", "alert", "crit", "err", "warning", "notice", "info", "debug"];
+
+// Syslog priority: facility * 8 + severity
+pub fn priority(facility: &str, severity: &str) -> Result<u8, String> {
+    let f = FACILITIES.iter().position(|f| *f == facility)
+        .ok_or(format!("unknown syslog facility `{}`", facility))?;
+    let s = SEVERITIES.iter().position(|s| *s == severity)
+        .ok_or(format!("unknown syslog severity `{}`", severity))?;
+    Ok((f * 8 + s) as u8)
+}
+
+pub fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|h| h.trim().to_string())
+        .unwrap_or_else(|_| String::from("-"))
+}
+
+// Message formatter, kept apart from the socket for testing
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    pub format: SyslogFormat,
+    pub pri: u8,
+    pub hostname: Option<String>,   // None => omitted (rfc3164 over /dev/log, like glibc)
+    pub tag: String,
+    pub pid: u32,
+}
+
+impl Formatter {
+    pub fn format(&self, msg: &str, now: DateTime<Local>) -> String {
+        match self.format {
+            SyslogFormat::Rfc3164 => {
+                let mut res = format!("<{}>{} ", self.pri, now.format("%b %e %H:%M:%S"));
+                if let Some(host) = &self.hostname {
+                    res.push_str(host);
+                    res.push(' ');
+                }
+                res.push_str(&format!("{}[{}]: {}", self.tag, self.pid, msg));
+                res
+            },
+            SyslogFormat::Rfc5424 => format!("<{}>1 {} {} {} {} - - {}", self.pri,
+                now.to_rfc3339_opts(SecondsFormat::Micros, false),
+                self.hostname.as_deref().unwrap_or("-"), self.tag, self.pid, msg),
+        }
+    }
+}
+
+enum Conn {
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl Conn {
+    fn open(config: &SyslogConfig) -> std::io::Result<Conn> {
+        match config.transport {
+            Transport::Unix => {
+                let sock = UnixDatagram::unbound()?;
+                sock.connect(&config.address)?;
+                Ok(Conn::Unix(sock))
+            },
+            Transport::Udp => {
+                let sock = UdpSocket::bind("0.0.0.0:0")?;
+                sock.connect(&config.address)?;
+                Ok(Conn::Udp(sock))
+            },
+            Transport::Tcp => Ok(Conn::Tcp(TcpStream::connect(&config.address)?)),
+        }
+    }
+
+    fn send(&mut self, msg: &str, format: SyslogFormat) -> std::io::Result<()> {
+        match self {
+            Conn::Unix(sock) => sock.send(msg.as_bytes()).map(|_| ()),
+            Conn::Udp(sock) => sock.send(msg.as_bytes()).map(|_| ()),
+            Conn::Tcp(stream) => match format {
+                SyslogFormat::Rfc5424 => write!(stream, "{} {}", msg.len(), msg),
+                SyslogFormat::Rfc3164 => writeln!(stream, "{}", msg),
+            },
+        }
+    }
+}
+
+pub struct SyslogSink {
+    config: SyslogConfig,
+    formatter: Formatter,
+    conn: Option<Conn>,
+}
+
+impl SyslogSink {
+    pub fn new(config: &SyslogConfig) -> Result<SyslogSink, String> {
+        let formatter = Formatter {
+            format: config.format,
+            pri: priority(&config.facility, &config.severity)?,
+            hostname: if config.transport == Transport::Unix && config.format == SyslogFormat::Rfc3164 {
+                None
+            } else {
+                Some(hostname())
+            },
+            tag: config.tag.clone(),
+            pid: std::process::id(),
+        };
+        Ok(SyslogSink { config: config.clone(), formatter, conn: None })
+    }
+}
+
+impl Sink for SyslogSink {
+    // (Re)connects on demand, a message that can't be sent after a reconnection is dropped
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        let line = self.formatter.format(&msg.text, Local::now());
+        let mut error = String::new();
+        for _ in 0..2 {
+            if self.conn.is_none() {
+                match Conn::open(&self.config) {
+                    Ok(c) => self.conn = Some(c),
+                    Err(e) => {
+                        error = format!("cannot connect: {}", e);
+                        thread::sleep(Duration::from_millis(100));
+                        continue;
+                    },
+                }
+            }
+            match self.conn.as_mut().map(|c| c.send(&line, self.config.format)) {
+                Some(Ok(())) => return Ok(()),
+                Some(Err(e)) => error = e.to_string(),
+                None => {},
+            }
+            self.conn = None;
+        }
+        Err(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sink::{Direction, Message, Severity, Sink};
+    use crate::syslog::{Formatter, SyslogConfig, SyslogFormat, SyslogSink, Transport, priority};
+    use chrono::{Local, TimeZone};
+    use std::net::UdpSocket;
+    use std::time::Duration;
+
+    #[test]
+    fn format_test() {
+        assert_eq!(priority("local6", "info"), Ok(182));
+        assert!(priority("local9", "info").is_err());
+
+        let now = Local.with_ymd_and_hms(2026, 3, 5, 8, 9, 10).unwrap();
+        let mut formatter = Formatter {
+            format: SyslogFormat::Rfc3164,
+            pri: 182,
+            hostname: None,
+            tag: String::from("elogj.info"),
+            pid: 42,
+        };
+        assert_eq!(formatter.format("a --> b - DROP", now), "<182>Mar  5 08:09:10 elogj.info[42]: a --> b - DROP");
+
+        formatter.format = SyslogFormat::Rfc5424;
+        formatter.hostname = Some(String::from("host"));
+        let line = formatter.format("msg", now);
+        assert!(line.starts_with("<182>1 2026-03-05T08:09:10.000000"));
+        assert!(line.ends_with(" host elogj.info 42 - - msg"));
+    }
+
+    #[test]
+    fn udp_test() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let config = SyslogConfig {
+            transport: Transport::Udp,
+            address: server.local_addr().unwrap().to_string(),
+            format: SyslogFormat::Rfc5424,
+            ..SyslogConfig::default()
+        };
+        let mut sink = SyslogSink::new(&config).unwrap();
+        sink.write(&Message::test("a --> b - PASS", Severity::Info, Direction::Outbound)).unwrap();
+
+        let mut buf = [0u8; 512];
+        let len = server.recv(&mut buf).unwrap();
+        let line = String::from_utf8_lossy(&buf[..len]);
+        assert!(line.starts_with("<182>1 "));
+        assert!(line.ends_with(&format!("elogj.info {} - - a --> b - PASS", std::process::id())));
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use hmac::{Hmac, Mac};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+use crate::sink::{Message, Sink};
+use crate::syslog::hostname;
+
+/** Webhook sink:
+ * POSTs batches of JSON alerts (event records) to `url`:
+ *
+ *  {"host": "...", "count": 2, "alerts": [{...}, {...}]}
+ *
+ * Only warning (alert) and critical (drop) events are sent unless
+ * `min_severity` says otherwise. A failed POST is retried with exponential
+ * backoff (`backoff_ms` doubled up to `max_backoff_ms`), meanwhile batches
+ * go to the spool directory (oldest files removed past `spool_max` bytes)
+ * and are sent first once the endpoint is back. Without spool, a batch is
+ * dropped after `retries` failed attempts.
+ * With `hmac_secret`, the body is signed: `X-Elogj-Signature: sha256=<hex>`.
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+    pub batch: usize,
+    pub retries: u32,   // attempts per batch without spool, spooled batches are kept until sent (or past spool_max)
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub spool: Option<String>,
+    pub spool_max: u64,
+    pub hmac_secret: Option<String>,
+    pub timeout: u64,   // seconds
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            url: String::new(),
+            headers: BTreeMap::new(),
+            batch: 20,
+            retries: 5,
+            backoff_ms: 500,
+            max_backoff_ms: 60_000,
+            spool: Some(String::from("/var/spool/elogj/webhook")),
+            spool_max: 64 * 1024 * 1024,
+            hmac_secret: None,
+            timeout: 10,
+        }
+    }
+}
+
+pub const SIGNATURE_HEADER: &str = "X-Elogj-Signature";
+
+// sha256=<hex> of the HMAC-SHA256 of body
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts any key size");
+    mac.update(body);
+    let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+pub struct WebhookSink {
+    config: WebhookConfig,
+    agent: ureq::Agent,
+    host: String,
+    alerts: Vec<String>,
+    backoff: Duration,
+    next_try: Instant,
+    attempts: u32,
+    failed: Option<Vec<u8>>,    // batch being retried (no spool)
+    seq: u64,
+}
+
+impl WebhookSink {
+    pub fn new(config: &WebhookConfig) -> Result<WebhookSink, String> {
+        if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+            return Err(format!("webhook: invalid url `{}`", config.url));
+        }
+        if let Some(dir) = &config.spool {
+            if !Path::new(dir).is_absolute() {
+                return Err(format!("webhook: spool must be an absolute path (`{}`)", dir));
+            }
+            fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)
+                .map_err(|e| format!("webhook: create {}: {}", dir, e))?;
+        }
+        Ok(WebhookSink {
+            config: config.clone(),
+            agent: ureq::AgentBuilder::new().timeout(Duration::from_secs(config.timeout.max(1))).build(),
+            host: hostname(),
+            alerts: Vec::new(),
+            backoff: Duration::from_millis(config.backoff_ms),
+            next_try: Instant::now(),
+            attempts: 0,
+            failed: None,
+            seq: 0,
+        })
+    }
+
+    fn body(&self) -> Vec<u8> {
+        format!("{{\"host\":{},\"count\":{},\"alerts\":[{}]}}", serde_json::to_string(&self.host).unwrap_or_default(),
+            self.alerts.len(), self.alerts.join(",")).into_bytes()
+    }
+
+    fn post(&self, body: &[u8]) -> Result<(), String> {
+        let mut request = self.agent.post(&self.config.url).set("Content-Type", "application/json");
+        for (name, value) in &self.config.headers {
+            request = request.set(name, value);
+        }
+        if let Some(secret) = &self.config.hmac_secret {
+            request = request.set(SIGNATURE_HEADER, &signature(secret, body));
+        }
+        request.send_bytes(body).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn succeeded(&mut self) {
+        self.backoff = Duration::from_millis(self.config.backoff_ms);
+        self.attempts = 0;
+    }
+
+    fn failed(&mut self) {
+        self.attempts += 1;
+        self.next_try = Instant::now() + self.backoff;
+        self.backoff = (self.backoff * 2).min(Duration::from_millis(self.config.max_backoff_ms.max(self.config.backoff_ms)));
+    }
+
+    // Spooled batches, oldest first
+    fn spooled(dir: &str) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir).map(|entries| entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+            .collect()).unwrap_or_default();
+        files.sort();
+        files
+    }
+
+    fn spool(&mut self, dir: &str, body: &[u8]) -> Result<(), String> {
+        let ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        self.seq += 1;
+        let path = Path::new(dir).join(format!("{:020}-{:06}.json", ns, self.seq % 1_000_000));
+        OpenOptions::new().create_new(true).write(true).mode(0o600).open(&path)
+            .and_then(|mut f| f.write_all(body))
+            .map_err(|e| format!("spool {}: {}", path.display(), e))?;
+
+        // Size limit: oldest batches go first
+        let files = Self::spooled(dir);
+        let mut size: u64 = files.iter().filter_map(|f| fs::metadata(f).ok()).map(|m| m.len()).sum();
+        let mut removed = 0;
+        for file in &files {
+            if size <= self.config.spool_max {
+                break;
+            }
+            size -= fs::metadata(file).map(|m| m.len()).unwrap_or(0);
+            let _ = fs::remove_file(file);
+            removed += 1;
+        }
+        if removed != 0 {
+            return Err(format!("spool full, {} batch(es) dropped", removed));
+        }
+        Ok(())
+    }
+
+    // Sends the spooled batches (stops at the first failure)
+    fn drain(&mut self, dir: &str) -> Result<(), String> {
+        for file in Self::spooled(dir) {
+            let body = fs::read(&file).map_err(|e| format!("spool {}: {}", file.display(), e))?;
+            if let Err(e) = self.post(&body) {
+                self.failed();
+                return Err(e);
+            }
+            self.succeeded();
+            let _ = fs::remove_file(&file);
+        }
+        Ok(())
+    }
+
+    fn send(&mut self) -> Result<(), String> {
+        let body = (!self.alerts.is_empty()).then(|| self.body());
+        self.alerts.clear();
+        match self.config.spool.clone() {
+            Some(dir) => self.send_spooled(&dir, body),
+            None => self.send_direct(body),
+        }
+    }
+
+    // Spool first while the endpoint is down (or spooled batches are left)
+    fn send_spooled(&mut self, dir: &str, body: Option<Vec<u8>>) -> Result<(), String> {
+        if Instant::now() < self.next_try || self.drain(dir).is_err() {
+            return body.map_or(Ok(()), |b| self.spool(dir, &b));
+        }
+        let Some(body) = body else {
+            return Ok(());
+        };
+        match self.post(&body) {
+            Ok(()) => {
+                self.succeeded();
+                Ok(())
+            },
+            Err(e) => {
+                self.failed();
+                self.spool(dir, &body)?;
+                Err(format!("{} (spooled)", e))
+            },
+        }
+    }
+
+    // A single batch is retried at a time, newer batches are dropped meanwhile
+    fn send_direct(&mut self, body: Option<Vec<u8>>) -> Result<(), String> {
+        if let Some(body) = body {
+            if self.failed.is_some() {
+                return Err(String::from("endpoint down, batch dropped"));
+            }
+            self.failed = Some(body);
+        }
+        if Instant::now() < self.next_try {
+            return Ok(());
+        }
+        let Some(body) = self.failed.take() else {
+            return Ok(());
+        };
+        match self.post(&body) {
+            Ok(()) => {
+                self.succeeded();
+                Ok(())
+            },
+            Err(e) => {
+                self.failed();
+                if self.attempts >= self.config.retries.max(1) {
+                    self.attempts = 0;
+                    return Err(format!("{}, batch dropped after {} attempt(s)", e, self.config.retries.max(1)));
+                }
+                self.failed = Some(body);
+                Err(format!("{} (retrying)", e))
+            },
+        }
+    }
+}
+
+impl Sink for WebhookSink {
+    fn write(&mut self, msg: &Message) -> Result<(), String> {
+        self.alerts.push(msg.json.clone());
+        if self.alerts.len() >= self.config.batch.max(1) {
+            return self.send();
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.send()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sink::{Direction, Message, Severity, Sink};
+    use crate::sink::test_util::http_server;
+    use crate::webhook::{signature, WebhookConfig, WebhookSink};
+
+    #[test]
+    fn spool_test() {
+        let alert = |id: u32| Message {
+            json: format!("{{\"id\":{}}}", id),
+            ..Message::test(&format!("alert {}", id), Severity::Critical, Direction::Outbound)
+        };
+
+        // Endpoint stand-in: answers 503 to the first request, then 200
+        let (addr, rx) = http_server(|n| match n {
+            0 => ("503 Service Unavailable", String::new()),
+            _ => ("200 OK", String::new()),
+        });
+        let url = format!("http://{}/hook", addr);
+
+        let spool = std::env::temp_dir().join(format!("elogj-webhook-{}", std::process::id()));
+        let config = WebhookConfig {
+            url,
+            batch: 2,
+            backoff_ms: 1,
+            spool: Some(spool.display().to_string()),
+            hmac_secret: Some(String::from("secret")),
+            ..WebhookConfig::default()
+        };
+        let mut sink = WebhookSink::new(&config).unwrap();
+        sink.write(&alert(1)).unwrap();
+        assert!(sink.write(&alert(2)).unwrap_err().contains("spooled"));   // 503
+        let request = rx.recv().unwrap();
+        let body = request.body;
+        assert_eq!(request.headers["x-elogj-signature"], signature("secret", &body));
+        assert!(String::from_utf8_lossy(&body).ends_with("\"count\":2,\"alerts\":[{\"id\":1},{\"id\":2}]}"));
+        assert_eq!(std::fs::read_dir(&spool).unwrap().count(), 1);
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        sink.write(&alert(3)).unwrap();
+        sink.flush().unwrap();  // spooled batch first, then the pending one
+        assert_eq!(rx.recv().unwrap().body, body);
+        assert!(String::from_utf8_lossy(&rx.recv().unwrap().body).ends_with("\"alerts\":[{\"id\":3}]}"));
+        assert_eq!(std::fs::read_dir(&spool).unwrap().count(), 0);
+        std::fs::remove_dir_all(&spool).unwrap();
+
+        assert_eq!(signature("key", b"The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+    }
+}
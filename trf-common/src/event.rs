@@ -0,0 +1,743 @@
+use core::fmt;
+use std::{format, vec, net::Ipv4Addr, string::{String, ToString}, vec::Vec};
+use serde::Serialize;
+use crate::{EventLog, EVENT_VERSION, EVENT_HEADER_LEN, INTERP_SIGS, FAMILY_JNDI, REASON_NONE, DNS_CORRELATED, DNS_LONG_LABEL,
+    DNS_KEY_PREFIX, DNS_ENTROPY, family_label, reason_label, location_label};
+
+/** Event (user feature):
+ * Typed view of an EventLog, decoded with TryFrom (EventLog or perf buffer bytes).
+ * Display renders the log line, Serialize the structured record (JSON, ...).
+ *
+ *  Traffic::Outbound --> XDP (etype 0): TCP / HTTP / LDAP / JRMP / Java payload / DNS
+ *  Traffic::Inbound  --> TC (etype 1): injection (interpolation) matches
+**/
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub version: u32,
+    pub timestamp_ns: u64,
+    pub src: Ipv4Addr,
+    pub dst: Ipv4Addr,
+    pub src_port: u16,
+    pub dst_port: u16,
+    pub protocol: u8,
+    pub ifindex: u32,
+    pub flow_id: u32,
+    pub pid: Option<u32>,
+    pub verdict: Verdict,
+    pub alert: bool,
+    pub reason: Option<Reason>,
+    pub whitelist: Option<Whitelist>,
+    pub signature: Option<u32>,
+    pub snippet: Option<Snippet>,
+    pub traffic: Traffic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Pass,
+    Drop,
+    Override,   // dropped by a rule, passed by a whitelist entry
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reason {
+    pub code: u32,
+    pub label: &'static str,
+    pub rule: Option<u32>,  // None => default rule
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Whitelist {
+    pub entry: u32,             // index + 1 in the rule file whitelist
+    pub host: Option<Ipv4Addr>, // set by the caller (EventLog only carries the entry)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snippet {
+    pub location: &'static str,
+    pub text: String,           // escaped, see escape
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "direction", rename_all = "lowercase")]
+pub enum Traffic {
+    Outbound(Outbound),
+    Inbound(Injection),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Outbound {
+    pub tcp: bool,
+    pub http: Option<Http>,
+    pub ldap: Option<Ldap>,
+    pub jrmp: Option<Jrmp>,
+    pub java: Option<JavaPayload>,
+    pub dns: Option<Dns>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Http {
+    Get,
+    Response,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Ldap {
+    pub op: LdapOp,
+    pub base_dn: Option<String>,    // searchRequest only
+    pub size: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum LdapOp {
+    #[serde(rename = "bindRequest")]
+    BindRequest,
+    #[serde(rename = "unbindRequest")]
+    UnbindRequest,
+    #[serde(rename = "searchRequest")]
+    SearchRequest,
+    #[serde(rename = "bindResponse")]
+    BindResponse,
+    #[serde(rename = "searchResEntry")]
+    SearchResEntry,
+    #[serde(rename = "searchResDone")]
+    SearchResDone,
+    #[serde(rename = "unknown")]
+    Unknown(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum Jrmp {
+    StreamProtocol,
+    SingleOpProtocol,
+    MultiplexProtocol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JavaPayload {
+    ClassFile,
+    SerializedObject,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dns {
+    pub qname: String,
+    pub correlated: bool,
+    pub long_label: bool,
+    pub key_prefix: bool,
+    pub high_entropy: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Injection {
+    pub interpolation: bool,        // `${`
+    pub jndi: bool,                 // `${jndi`
+    pub ldap: bool,                 // `${jndi:ldap`
+    pub family: Option<&'static str>,
+    pub pattern: Option<String>,    // matched INTERP_SIGS pattern
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventError {
+    Truncated(usize),   // record size
+    Version(u32),       // EventLog version
+    Type(u32),          // etype
+    Signature(u32),     // INTERP_SIGS idx + 1
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventError::Truncated(len) => write!(f, "truncated event: {} bytes (header: {} bytes + snippet)",
+                len, EVENT_HEADER_LEN),
+            EventError::Version(v) => write!(f, "event version v{} (expected v{})", v, EVENT_VERSION),
+            EventError::Type(t) => write!(f, "unknown event type {}", t),
+            EventError::Signature(s) => write!(f, "unknown interpolation signature {}", s),
+        }
+    }
+}
+
+impl std::error::Error for EventError {}
+
+// Zero padded bytes to String
+fn padded(raw: &[u8]) -> String {
+    let len = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+    String::from_utf8_lossy(&raw[..len]).into_owned()
+}
+
+// QNAME (length prefixed labels) to dotted notation
+fn qname(raw: &[u8]) -> String {
+    let mut labels: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < raw.len() && raw[i] != 0 {
+        let end = (i + 1 + raw[i] as usize).min(raw.len());
+        labels.push(String::from_utf8_lossy(&raw[i + 1..end]).into_owned());
+        i = end;
+    }
+    labels.join(".")
+}
+
+// Payload snippet escaped for logging: printable ASCII is kept, `\`, `"` and other bytes are escaped
+pub fn escape(raw: &[u8]) -> String {
+    let mut res = String::new();
+    for b in raw {
+        match b {
+            b'\\' => res.push_str("\\\\"),
+            b'"' => res.push_str("\\\""),
+            0x20..=0x7e => res.push(*b as char),
+            _ => res.push_str(&format!("\\x{:02x}", b)),
+        }
+    }
+    res
+}
+
+fn outbound(data: &EventLog) -> Outbound {
+    let levls = data.elvls;
+    let ldap = match levls[2] {
+        0 => None,
+        op => {
+            let op = match op {
+                96 => LdapOp::BindRequest,
+                66 => LdapOp::UnbindRequest,
+                99 => LdapOp::SearchRequest,
+                97 => LdapOp::BindResponse,
+                100 => LdapOp::SearchResEntry,
+                101 => LdapOp::SearchResDone,
+                _ => LdapOp::Unknown(op),
+            };
+            let base_dn = if op == LdapOp::SearchRequest { Some(padded(&data.edn)) } else { None };
+            Some(Ldap { op, base_dn, size: levls[1] })   // elvls[1] holds the LDAP packet size
+        },
+    };
+    let http = match levls[1] {
+        1 if ldap.is_none() => Some(Http::Get),
+        2 if ldap.is_none() => Some(Http::Response),
+        _ => None,
+    };
+    let jrmp = match levls[3] {
+        75 => Some(Jrmp::StreamProtocol),
+        76 => Some(Jrmp::SingleOpProtocol),
+        77 => Some(Jrmp::MultiplexProtocol),
+        _ => None,
+    };
+    let java = match levls[4] {
+        1 => Some(JavaPayload::ClassFile),
+        2 => Some(JavaPayload::SerializedObject),
+        _ => None,
+    };
+    let dns = match levls[5] {
+        0 => None,
+        flags => Some(Dns {
+            qname: qname(&data.edn),
+            correlated: flags & DNS_CORRELATED != 0,
+            long_label: flags & DNS_LONG_LABEL != 0,
+            key_prefix: flags & DNS_KEY_PREFIX != 0,
+            high_entropy: flags & DNS_ENTROPY != 0,
+        }),
+    };
+    Outbound { tcp: levls[0] == 1, http, ldap, jrmp, java, dns }
+}
+
+fn inbound(data: &EventLog) -> Result<Injection, EventError> {
+    let levls = data.elvls;
+    let mut injection = Injection {
+        interpolation: levls[0] == 1,
+        jndi: levls[1] == 1,
+        ldap: levls[2] == 1,
+        family: None,
+        pattern: None,
+    };
+    if levls[3] != 0 {
+        let sig = INTERP_SIGS.get((levls[4] as usize).wrapping_sub(1)).ok_or(EventError::Signature(levls[4]))?;
+        injection.family = Some(family_label(levls[3]));
+        injection.pattern = Some(String::from_utf8_lossy(&sig.pattern[..sig.len]).into_owned());
+    }
+    Ok(injection)
+}
+
+impl TryFrom<EventLog> for Event {
+    type Error = EventError;
+
+    fn try_from(data: EventLog) -> Result<Self, Self::Error> {
+        if data.eversion != EVENT_VERSION {
+            return Err(EventError::Version(data.eversion));
+        }
+        let traffic = match data.etype {
+            0 => Traffic::Outbound(outbound(&data)),
+            1 => Traffic::Inbound(inbound(&data)?),
+            t => return Err(EventError::Type(t)),
+        };
+
+        let verdict = if data.eaction[0] == 1 {
+            Verdict::Drop
+        } else if data.eaction[1] == 1 {
+            Verdict::Override
+        } else {
+            Verdict::Pass
+        };
+        let reason = match data.ereason[0] {
+            REASON_NONE => None,
+            code => Some(Reason {
+                code,
+                label: reason_label(code),
+                rule: if data.ereason[1] != 0 { Some(data.ereason[1]) } else { None },
+            }),
+        };
+        let whitelist = match data.ereason[2] {
+            0 => None,
+            entry => Some(Whitelist { entry, host: None }),
+        };
+        let snip_len = (data.esnip[1] as usize).min(data.esnippet.len());
+        let snippet = match snip_len {
+            0 => None,
+            len => Some(Snippet { location: location_label(data.esnip[0]), text: escape(&data.esnippet[..len]) }),
+        };
+
+        Ok(Event {
+            version: data.eversion,
+            timestamp_ns: data.ets,
+            src: Ipv4Addr::from(data.eroute[0]),
+            dst: Ipv4Addr::from(data.eroute[1]),
+            src_port: data.eports[0] as u16,
+            dst_port: data.eports[1] as u16,
+            protocol: data.eproto as u8,
+            ifindex: data.eifindex,
+            flow_id: data.eflow,
+            pid: if data.epid[0] != 0 { Some(data.epid[0]) } else { None },
+            verdict,
+            alert: data.eaction[2] == 1,
+            reason,
+            whitelist,
+            signature: if data.esig != 0 { Some(data.esig) } else { None },
+            snippet,
+            traffic,
+        })
+    }
+}
+
+// Ring buffer / perf buffer record (see EventLog::from_bytes)
+impl TryFrom<&[u8]> for Event {
+    type Error = EventError;
+
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
+        let data = EventLog::from_record(buf).ok_or(EventError::Truncated(buf.len()))?;
+        if data.eversion == EVENT_VERSION && data.esnip[1] as usize > buf.len() - EVENT_HEADER_LEN {
+            return Err(EventError::Truncated(buf.len()));
+        }
+        Event::try_from(data)
+    }
+}
+
+impl Event {
+    // Detection tags (stable names, see Record)
+    pub fn detections(&self) -> Vec<String> {
+        let mut res: Vec<&str> = Vec::new();
+        match &self.traffic {
+            Traffic::Outbound(outbound) => {
+                if outbound.tcp {
+                    res.push("tcp");
+                }
+                match outbound.http {
+                    Some(Http::Get) => res.push("http_get"),
+                    Some(Http::Response) => res.push("http_response"),
+                    None => {},
+                }
+                if outbound.ldap.is_some() {
+                    res.push("ldap");
+                }
+                if outbound.jrmp.is_some() {
+                    res.push("jrmp");
+                }
+                match outbound.java {
+                    Some(JavaPayload::ClassFile) => res.push("java_class_file"),
+                    Some(JavaPayload::SerializedObject) => res.push("java_serialized_object"),
+                    None => {},
+                }
+                if let Some(dns) = &outbound.dns {
+                    res.push("dns_query");
+                    if dns.correlated {
+                        res.push("dns_correlated");
+                    }
+                    if dns.long_label {
+                        res.push("dns_long_label");
+                    }
+                    if dns.key_prefix {
+                        res.push("dns_key_prefix");
+                    }
+                    if dns.high_entropy {
+                        res.push("dns_high_entropy");
+                    }
+                }
+            },
+            Traffic::Inbound(injection) => {
+                if injection.interpolation {
+                    res.push("interpolation");
+                }
+                if injection.jndi {
+                    res.push("jndi");
+                }
+                if injection.ldap {
+                    res.push("jndi_ldap");
+                }
+            },
+        }
+        let mut res: Vec<String> = res.into_iter().map(String::from).collect();
+        if let Traffic::Inbound(Injection { family: Some(family), .. }) = &self.traffic {
+            if *family != family_label(FAMILY_JNDI) {
+                res.push(family.to_lowercase().replace(' ', "_"));
+            }
+        }
+        if self.signature.is_some() {
+            res.push(String::from("signature"));
+        }
+        res
+    }
+
+    // Structured fields (journald: ELOGJ_<name>), only set values are listed
+    pub fn fields(&self) -> Vec<(&'static str, String)> {
+        let mut res: Vec<(&'static str, String)> = vec![
+            ("SRC", self.src.to_string()),
+            ("DST", self.dst.to_string()),
+        ];
+        if self.src_port != 0 || self.dst_port != 0 {
+            res.push(("SRC_PORT", self.src_port.to_string()));
+            res.push(("DST_PORT", self.dst_port.to_string()));
+        }
+        res.push(("PROTOCOL", match self.protocol {
+            6 => String::from("tcp"),
+            17 => String::from("udp"),
+            1 => String::from("icmp"),
+            p => p.to_string(),
+        }));
+        res.push(("DIRECTION", String::from(match self.traffic {
+            Traffic::Outbound(_) => "OUTBOUND",
+            Traffic::Inbound(_) => "INBOUND",
+        })));
+        res.push(("VERDICT", String::from(match self.verdict {
+            Verdict::Pass => "PASS",
+            Verdict::Drop => "DROP",
+            Verdict::Override => "OVERRIDE",
+        })));
+        if self.alert {
+            res.push(("ALERT", String::from("1")));
+        }
+        if let Some(reason) = &self.reason {
+            res.push(("REASON", String::from(reason.label)));
+            if let Some(rule) = reason.rule {
+                res.push(("RULE_ID", rule.to_string()));
+            }
+        }
+        if let Some(id) = self.signature {
+            res.push(("SIGNATURE_ID", id.to_string()));
+        }
+        let detections = self.detections();
+        if !detections.is_empty() {
+            res.push(("DETECTIONS", detections.join(",")));
+        }
+        res.push(("FLOW_ID", format!("{:08x}", self.flow_id)));
+        res
+    }
+}
+
+/** Record:
+ * Flat event record with stable field names (JSON output), new fields
+ * may be added but existing ones are never renamed.
+ * timestamp is wall clock (RFC 3339), set by the caller from ets.
+**/
+#[derive(Debug, Serialize)]
+pub struct Record<'a> {
+    pub timestamp: String,
+    pub version: u32,
+    pub direction: &'static str,
+    pub src_ip: Ipv4Addr,
+    pub src_port: u16,
+    pub dst_ip: Ipv4Addr,
+    pub dst_port: u16,
+    pub protocol: u8,
+    pub verdict: Verdict,
+    pub alert: bool,
+    pub reason: Option<&'a str>,
+    pub rule_id: Option<u32>,
+    pub whitelist: Option<&'a Whitelist>,
+    pub detections: Vec<String>,
+    pub signature_id: Option<u32>,
+    pub ldap_op: Option<LdapOp>,
+    pub ldap_base_dn: Option<&'a str>,
+    pub interpolation: Option<&'a str>,
+    pub dns_query: Option<&'a str>,
+    pub snippet: Option<&'a Snippet>,
+    pub flow_id: String,
+    pub ifindex: u32,
+    pub pid: Option<u32>,
+}
+
+impl<'a> Record<'a> {
+    pub fn new(event: &'a Event, timestamp: String) -> Self {
+        let (direction, ldap, dns, interpolation) = match &event.traffic {
+            Traffic::Outbound(outbound) => ("outbound", outbound.ldap.as_ref(), outbound.dns.as_ref(), None),
+            Traffic::Inbound(injection) => ("inbound", None, None, injection.pattern.as_deref()),
+        };
+        Record {
+            timestamp,
+            version: event.version,
+            direction,
+            src_ip: event.src,
+            src_port: event.src_port,
+            dst_ip: event.dst,
+            dst_port: event.dst_port,
+            protocol: event.protocol,
+            verdict: event.verdict,
+            alert: event.alert,
+            reason: event.reason.as_ref().map(|r| r.label),
+            rule_id: event.reason.as_ref().and_then(|r| r.rule),
+            whitelist: event.whitelist.as_ref(),
+            detections: event.detections(),
+            signature_id: event.signature,
+            ldap_op: ldap.map(|l| l.op),
+            ldap_base_dn: ldap.and_then(|l| l.base_dn.as_deref()),
+            interpolation,
+            dns_query: dns.map(|d| d.qname.as_str()),
+            snippet: event.snippet.as_ref(),
+            flow_id: format!("{:08x}", event.flow_id),
+            ifindex: event.ifindex,
+            pid: event.pid,
+        }
+    }
+}
+
+impl fmt::Display for Outbound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.tcp {
+            write!(f, " TCP Traffic;")?;
+        }
+        match self.http {
+            Some(Http::Get) => write!(f, " HTTP GET;")?,
+            Some(Http::Response) => write!(f, " HTTP Resp;")?,
+            None => {},
+        }
+        if let Some(ldap) = &self.ldap {
+            match ldap.op {
+                LdapOp::Unknown(_) => {},
+                op => write!(f, " {}", ldap_op_label(&op))?,
+            }
+            if let Some(dn) = &ldap.base_dn {
+                write!(f, " - baseDN: {}", dn)?;
+            }
+            write!(f, " - size: {} bytes;", ldap.size)?;
+        }
+        if let Some(jrmp) = self.jrmp {
+            write!(f, " JRMP {:?};", jrmp)?;
+        }
+        match self.java {
+            Some(JavaPayload::ClassFile) => write!(f, " Java class file;")?,
+            Some(JavaPayload::SerializedObject) => write!(f, " Java serialized object;")?,
+            None => {},
+        }
+        if let Some(dns) = &self.dns {
+            write!(f, " DNS query: {}", dns.qname)?;
+            if dns.correlated {
+                write!(f, " - correlated")?;
+            }
+            if dns.long_label {
+                write!(f, " - long label")?;
+            }
+            if dns.key_prefix {
+                write!(f, " - key prefix")?;
+            }
+            if dns.high_entropy {
+                write!(f, " - high entropy")?;
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+// LdapOp protocol name (same as the serialized one)
+pub(crate) fn ldap_op_label(op: &LdapOp) -> &'static str {
+    match op {
+        LdapOp::BindRequest => "bindRequest",
+        LdapOp::UnbindRequest => "unbindRequest",
+        LdapOp::SearchRequest => "searchRequest",
+        LdapOp::BindResponse => "bindResponse",
+        LdapOp::SearchResEntry => "searchResEntry",
+        LdapOp::SearchResDone => "searchResDone",
+        LdapOp::Unknown(_) => "unknown",
+    }
+}
+
+impl fmt::Display for Injection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.ldap {
+            write!(f, " `${{jndi:ldap` match;")
+        } else if self.jndi {
+            write!(f, " `${{jndi` match;")
+        } else if let (Some(pattern), Some(family)) = (&self.pattern, self.family.filter(|f| *f != family_label(FAMILY_JNDI))) {
+            write!(f, " `{}` match ({});", pattern, family)
+        } else if self.interpolation {
+            write!(f, " `${{` match;")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.src_port != 0 || self.dst_port != 0 {
+            write!(f, "{}:{} --> {}:{}", self.src, self.src_port, self.dst, self.dst_port)?;
+        } else {
+            write!(f, "{} --> {}", self.src, self.dst)?;
+        }
+        match self.verdict {
+            Verdict::Drop => write!(f, " - DROP")?,
+            Verdict::Override => write!(f, " - PASS**")?,
+            Verdict::Pass => write!(f, " - PASS")?,
+        }
+        if self.alert {
+            write!(f, " - ALERT")?;
+        }
+        if let Some(reason) = &self.reason {
+            write!(f, " - REASON: {}", reason.label)?;
+            if let Some(rule) = reason.rule {
+                write!(f, " (rule #{})", rule)?;
+            }
+        }
+        if let Some(whitelist) = &self.whitelist {
+            write!(f, " - whitelist #{}", whitelist.entry)?;
+            if let Some(host) = whitelist.host {
+                write!(f, " ({})", host)?;
+            }
+        }
+        write!(f, " - flow {:08x} - if {}", self.flow_id, self.ifindex)?;
+        if let Some(pid) = self.pid {
+            write!(f, " - pid {}", pid)?;
+        }
+        write!(f, " - LOG:")?;
+        if let Some(sig) = self.signature {
+            write!(f, " SIG {};", sig)?;
+        }
+        match &self.traffic {
+            Traffic::Outbound(outbound) => write!(f, "{}", outbound)?,
+            Traffic::Inbound(injection) => write!(f, "{}", injection)?,
+        }
+        if let Some(snippet) = &self.snippet {
+            write!(f, " snippet ({}): \"{}\";", snippet.location, snippet.text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, string::{String, ToString}};
+    use crate::{EventLog, EVENT_VERSION, EVENT_HEADER_LEN, LDAP_DN_LEN, SNIPPET_LEN, SIG_LOC_HEADER, FAMILY_COMMONS_TEXT,
+        REASON_INTERP, event_record_len};
+    use crate::event::{Event, EventError, Record, Traffic, LdapOp, Verdict};
+
+    fn log(etype: u32) -> EventLog {
+        EventLog {
+            eversion: EVENT_VERSION,
+            etype,
+            ets: 42,
+            eroute: [u32::from(Ipv4Addr::new(172, 17, 0, 2)), u32::from(Ipv4Addr::new(10, 0, 0, 1))],
+            eports: [0, 0],
+            eproto: 6,
+            eifindex: 3,
+            eflow: 0xabcd,
+            epid: [0, 0],
+            eaction: [0, 0, 0],
+            elvls: [0; 6],
+            ereason: [0, 0, 0],
+            esig: 0,
+            esnip: [0, 0],
+            edn: [0u8; LDAP_DN_LEN],
+            esnippet: [0u8; SNIPPET_LEN],
+        }
+    }
+
+    #[test]
+    fn outbound_test() {
+        let mut data = log(0);
+        data.eports = [40000, 1389];
+        data.eaction = [1, 0, 0];
+        data.elvls = [1, 120, 99, 0, 0, 0];
+        data.edn[..10].copy_from_slice(b"dc=example");
+
+        let event = Event::try_from(data).unwrap();
+        assert_eq!(event.verdict, Verdict::Drop);
+        match &event.traffic {
+            Traffic::Outbound(outbound) => {
+                let ldap = outbound.ldap.as_ref().unwrap();
+                assert_eq!((ldap.op, ldap.size), (LdapOp::SearchRequest, 120));
+                assert_eq!(ldap.base_dn.as_deref(), Some("dc=example"));
+                assert!(outbound.http.is_none());
+            },
+            _ => panic!("expected outbound traffic"),
+        }
+        assert_eq!(event.to_string(), "172.17.0.2:40000 --> 10.0.0.1:1389 - DROP - flow 0000abcd - if 3 - LOG: \
+            TCP Traffic; searchRequest - baseDN: dc=example - size: 120 bytes;");
+    }
+
+    #[test]
+    fn inbound_test() {
+        let mut data = log(1);
+        data.eaction = [0, 0, 1];
+        data.elvls = [1, 0, 0, FAMILY_COMMONS_TEXT, 2, 0];
+        data.ereason = [REASON_INTERP, 8, 0];
+        data.esnip = [SIG_LOC_HEADER, 14];
+        data.esnippet[..14].copy_from_slice(b"${script:\"x\"}\n");
+
+        let event = Event::try_from(data).unwrap();
+        assert_eq!(event.to_string(), "172.17.0.2 --> 10.0.0.1 - PASS - ALERT - REASON: interpolation (rule #8) \
+            - flow 0000abcd - if 3 - LOG: `${script:` match (Commons Text); snippet (header): \"${script:\\\"x\\\"}\\x0a\";");
+
+        let record = serde_json::to_value(Record::new(&event, String::from("2026-10-19T00:00:00Z"))).unwrap();
+        assert_eq!(record["direction"], "inbound");
+        assert_eq!(record["verdict"], "pass");
+        assert_eq!(record["reason"], "interpolation");
+        assert_eq!(record["rule_id"], 8);
+        assert_eq!(record["detections"], serde_json::json!(["interpolation", "commons_text"]));
+        assert_eq!(record["snippet"]["location"], "header");
+
+        let fields = event.fields();
+        assert!(fields.contains(&("VERDICT", String::from("PASS"))));
+        assert!(fields.contains(&("REASON", String::from("interpolation"))));
+        assert!(fields.contains(&("DETECTIONS", String::from("interpolation,commons_text"))));
+        assert!(!fields.iter().any(|(name, _)| *name == "SRC_PORT"));
+
+        let mut data = log(1);
+        data.eversion = EVENT_VERSION - 1;
+        assert_eq!(Event::try_from(data), Err(EventError::Version(EVENT_VERSION - 1)));
+        assert_eq!(Event::try_from(&[0u8; 8][..]), Err(EventError::Truncated(8)));
+    }
+
+    #[test]
+    fn record_test() {
+        let mut data = log(1);
+        data.esnip = [SIG_LOC_HEADER, 70];
+        data.esnippet[..70].copy_from_slice(&[b'a'; 70]);
+        let bytes = unsafe {
+            core::slice::from_raw_parts(&data as *const EventLog as *const u8, core::mem::size_of::<EventLog>())
+        };
+        assert_eq!(event_record_len(0), EVENT_HEADER_LEN);
+        assert_eq!(event_record_len(70), EVENT_HEADER_LEN + 128);
+        assert_eq!(event_record_len(SNIPPET_LEN), core::mem::size_of::<EventLog>());
+
+        // ring buffer record: header + snippet class, the rest is zeroed
+        let record = &bytes[..event_record_len(70)];
+        let event = EventLog::from_bytes(record).unwrap();
+        assert_eq!(&event.esnippet[..70], &[b'a'; 70][..]);
+        assert_eq!(event.esnippet[70..], [0u8; SNIPPET_LEN - 70]);
+        assert_eq!(Event::try_from(record), Event::try_from(data));
+        assert_eq!(Event::try_from(bytes), Event::try_from(data));
+
+        // snippet longer than the record
+        assert!(EventLog::from_bytes(&bytes[..EVENT_HEADER_LEN + 64]).is_none());
+        assert_eq!(Event::try_from(&bytes[..EVENT_HEADER_LEN + 64]), Err(EventError::Truncated(EVENT_HEADER_LEN + 64)));
+        assert_eq!(Event::try_from(&bytes[..EVENT_HEADER_LEN - 1]), Err(EventError::Truncated(EVENT_HEADER_LEN - 1)));
+    }
+}
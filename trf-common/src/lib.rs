@@ -1,29 +1,536 @@
 #![no_std]
 
-/** EventLog
+// Typed events (std, serde) for userspace consumers
+#[cfg(feature = "user")]
+extern crate std;
+#[cfg(feature = "user")]
+pub mod event;
+#[cfg(feature = "user")]
+pub mod siem;
+
+// Max number of base DN (or QNAME) bytes copied from an outbound LDAP searchRequest (or DNS query)
+pub const LDAP_DN_LEN: usize = 64;
+
+// Max number of payload bytes copied to esnippet (disabled with `capture_snippets: false`)
+pub const SNIPPET_LEN: usize = 256;
+
+/** DNS query flags (elvls[5] , etype 0)
+ * DNS_QUERY       --> Outbound DNS query
+ * DNS_CORRELATED  --> QNAME is the host of a `${jndi:<scheme>://host` lookup injected into
+ *                     the workload shortly before (see Injection)
+ * DNS_LONG_LABEL  --> First label length >= 24 (alert only: CDN / cloud host names)
+ * DNS_KEY_PREFIX  --> First label starts with a key-like prefix (AKIA / ASIA)
+ * DNS_ENTROPY     --> First label uses an unusually large character set (high entropy),
+ *                     see DNS_ENTROPY_CHARS
+**/
+pub const DNS_QUERY: u32 = 1;
+pub const DNS_CORRELATED: u32 = 2;
+pub const DNS_LONG_LABEL: u32 = 4;
+pub const DNS_KEY_PREFIX: u32 = 8;
+pub const DNS_ENTROPY: u32 = 16;
+pub const DNS_EXFIL: u32 = DNS_KEY_PREFIX | DNS_ENTROPY;    // dropped by the `exfil` block type
+
+// Min first label length to estimate its character set: shorter CDN / cloud labels are left out
+pub const DNS_ENTROPY_LABEL_LEN: u8 = 24;
+// Distinct characters --> ~log2(18) = 4.2 bits per char, host names made of words stay below it
+pub const DNS_ENTROPY_CHARS: u32 = 18;
+
+/** DNS character set:
+ * Folds a QNAME byte into a 128 bit set [lo , hi] (case insensitive,
+ * byte | 0x20), used to estimate the number of distinct characters
+ * in a label. Bytes past the label (mask 0) are not added.
+**/
+#[inline(always)]
+pub fn dns_charset_add(charset: &mut [u64; 2], byte: u8, mask: u64) {
+    let folded = (byte | 0x20) as u64;
+    let bit = (1u64 << (folded & 63)) & mask;
+    let high = (folded >> 6) & 1;
+    charset[0] |= bit & high.wrapping_sub(1);
+    charset[1] |= bit & high.wrapping_neg();
+}
+
+#[inline(always)]
+pub fn dns_charset_high_entropy(charset: &[u64; 2]) -> bool {
+    charset[0].count_ones() + charset[1].count_ones() >= DNS_ENTROPY_CHARS
+}
+
+/** Injected lookup (INJECTIONS , workload address --> Injection)
+ * Written by egtrf when an interpolation reaches a workload, host is the
+ * name_hash of the `${jndi:<scheme>://host` lookup host (0 if none).
+ * intrf compares it with the QNAME of the workload DNS queries (qname_hash).
+**/
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Injection {
+    pub ts: u64,        // bpf_ktime_get_ns
+    pub host: u32,
+    pub host_len: u32,
+}
+
+// Host names are compared through their FNV-1a hash (ASCII lowercase)
+pub const NAME_HASH_INIT: u32 = 0x811c9dc5;
+
+#[inline(always)]
+pub fn name_hash_step(hash: u32, byte: u8) -> u32 {
+    name_hash_mix(hash, byte.to_ascii_lowercase())
+}
+
+// name_hash_step of a byte already lowercased (see HOST_BYTES)
+#[inline(always)]
+pub fn name_hash_mix(hash: u32, lower: u8) -> u32 {
+    (hash ^ lower as u32).wrapping_mul(0x01000193)
+}
+
+pub const fn is_host_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'.' || byte == b'_'
+}
+
+/** Host bytes:
+ * Lowercase value of every byte that can be part of a host name, 0 for the others.
+ * The eBPF scans load it instead of branching on every character range.
+**/
+pub const HOST_BYTES: [u8; 256] = host_bytes();
+
+const fn host_bytes() -> [u8; 256] {
+    let mut bytes = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        if is_host_byte(i as u8) {
+            bytes[i] = (i as u8).to_ascii_lowercase();
+        }
+        i += 1;
+    }
+    bytes
+}
+
+// Hash of a dotted host name, up to the first byte that can't be part of it (0 if empty)
+pub fn host_hash(host: &[u8]) -> u32 {
+    let mut hash = NAME_HASH_INIT;
+    for (i, byte) in host.iter().enumerate() {
+        if i >= LDAP_DN_LEN || !is_host_byte(*byte) {
+            break;
+        }
+        hash = name_hash_step(hash, *byte);
+    }
+    if hash == NAME_HASH_INIT { 0 } else { hash }
+}
+
+/** QNAME hash:
+ * Hash of a QNAME (wire format, zero padded - see edn), same value as host_hash of the dotted name.
+ * Label length bytes are found without branching on `i == next`: the verifier would
+ * resolve it from the range of next and keep one state per label layout (intrf).
+**/
+pub fn qname_hash(qname: &[u8; LDAP_DN_LEN]) -> u32 {
+    let mut hash = NAME_HASH_INIT;
+    let mut next = qname[0] as usize + 1;   // next label length byte
+    for (i, byte) in qname.iter().enumerate().skip(1) {
+        let gap = core::hint::black_box(i ^ next);
+        let boundary = ((gap | gap.wrapping_neg()) >> (usize::BITS - 1)) ^ 1;    // 1 if i == next
+        if boundary == 1 && *byte == 0 {
+            break;
+        }
+        next += boundary * (*byte as usize + 1);
+        let mask = 0u8.wrapping_sub(boundary as u8);
+        hash = name_hash_step(hash, *byte ^ ((*byte ^ b'.') & mask));
+    }
+    if hash == NAME_HASH_INIT { 0 } else { hash }
+}
+
+/** Verdict reasons (ereason[0])
+ * Rule ids are set in the rule file (`id`, defaults to the block entry
+ * position starting at 1), signature ids for REASON_SIGNATURE and the
+ * rule that blocked the host for REASON_BLOCKLIST.
+**/
+pub const REASON_NONE: u32 = 0;
+pub const REASON_OUTBOUND_TCP: u32 = 1;     // RULE_SET idx 0 (1)
+pub const REASON_OUTBOUND_HTTP: u32 = 2;    // RULE_SET idx 0 (2)
+pub const REASON_LDAP_PORT: u32 = 3;        // RULE_SET idx 1
+pub const REASON_BLOCKLIST: u32 = 4;        // BLOCKLIST
+pub const REASON_LOOKUP: u32 = 5;           // RULE_SET idx 2/3 (lookup) - GET request after a JNDI lookup
+pub const REASON_JNDI_REQUEST: u32 = 6;     // RULE_SET idx 2/3 (request)
+pub const REASON_LDAP_REQUEST: u32 = 7;     // RULE_SET idx 4
+pub const REASON_JRMP: u32 = 8;             // RULE_SET idx 5
+pub const REASON_JAVA_OBJECT: u32 = 9;      // RULE_SET idx 6
+pub const REASON_DNS: u32 = 10;             // RULE_SET idx 7
+pub const REASON_INTERP: u32 = 11;          // RULE_SET idx 8/9
+pub const REASON_SIGNATURE: u32 = 12;       // SIGNATURES
+
+pub fn reason_label(reason: u32) -> &'static str {
+    match reason {
+        REASON_OUTBOUND_TCP => "outbound_tcp",
+        REASON_OUTBOUND_HTTP => "outbound_http",
+        REASON_LDAP_PORT => "ldap_port",
+        REASON_BLOCKLIST => "blocklist",
+        REASON_LOOKUP => "lookup_correlation",
+        REASON_JNDI_REQUEST => "jndi_request",
+        REASON_LDAP_REQUEST => "ldap_request",
+        REASON_JRMP => "jrmp",
+        REASON_JAVA_OBJECT => "java_object",
+        REASON_DNS => "dns",
+        REASON_INTERP => "interpolation",
+        REASON_SIGNATURE => "signature",
+        _ => "none",
+    }
+}
+
+/** Interpolation signatures
+ * Lookup prefixes matched at the start of the logger entry (header value),
+ * each signature belongs to a family. Families other than JNDI may be
+ * enabled/disabled independently (RULE_SET idx 8 and 9).
+**/
+pub const FAMILY_JNDI: u32 = 1;             // Log4Shell (CVE-2021-44228)
+pub const FAMILY_COMMONS_TEXT: u32 = 2;     // Text4Shell (CVE-2022-42889)
+pub const FAMILY_SPEL: u32 = 3;             // Spring Expression Language injection
+
+pub const INTERP_SIG_LEN: usize = 10;
+
+#[derive(Clone, Copy)]
+pub struct InterpSig {
+    pub pattern: [u8; INTERP_SIG_LEN],
+    pub len: usize,
+    pub family: u32,
+}
+
+const fn interp_sig(pattern: &[u8], family: u32) -> InterpSig {
+    let mut padded = [0u8; INTERP_SIG_LEN];
+    let mut i = 0;
+    while i < pattern.len() {
+        padded[i] = pattern[i];
+        i += 1;
+    }
+    InterpSig { pattern: padded, len: pattern.len(), family }
+}
+
+pub const INTERP_SIGS: [InterpSig; 6] = [
+    interp_sig(b"${jndi", FAMILY_JNDI),
+    interp_sig(b"${script:", FAMILY_COMMONS_TEXT),
+    interp_sig(b"${dns:", FAMILY_COMMONS_TEXT),
+    interp_sig(b"${url:", FAMILY_COMMONS_TEXT),
+    interp_sig(b"#{T(", FAMILY_SPEL),
+    interp_sig(b"${T(", FAMILY_SPEL),
+];
+
+pub fn family_label(family: u32) -> &'static str {
+    match family {
+        FAMILY_JNDI => "JNDI",
+        FAMILY_COMMONS_TEXT => "Commons Text",
+        FAMILY_SPEL => "SpEL",
+        _ => "Unknown",
+    }
+}
+
+/** Signatures
+ * User defined signatures (rule file `signatures:` section), compiled
+ * by logger-info and loaded to the SIGNATURES map.
+ * A packet byte b matches pattern[i] if (b & mask[i]) == pattern[i]:
+ *      mask 0xFF => exact byte , 0xDF => case insensitive letter , 0 => any byte
+ * Unused map entries have len == 0, entries are loaded contiguously.
+**/
+pub const SIG_MAX: u32 = 16;
+pub const SIG_PATTERN_LEN: usize = 32;
+
+// Signature location
+pub const SIG_LOC_HEADER: u32 = 1;
+pub const SIG_LOC_URI: u32 = 2;
+pub const SIG_LOC_BODY: u32 = 3;
+pub const SIG_LOC_LDAP: u32 = 4;
+pub const SIG_LOC_PAYLOAD: u32 = 5;
+
+pub fn location_label(location: u32) -> &'static str {
+    match location {
+        SIG_LOC_HEADER => "header",
+        SIG_LOC_URI => "uri",
+        SIG_LOC_BODY => "body",
+        SIG_LOC_LDAP => "ldap",
+        SIG_LOC_PAYLOAD => "payload",
+        _ => "-",
+    }
+}
+// Signature direction (same as etype + 1)
+pub const SIG_DIR_OUTBOUND: u32 = 1;
+pub const SIG_DIR_INBOUND: u32 = 2;
+// Signature action
+pub const SIG_ALERT: u32 = 1;
+pub const SIG_DROP: u32 = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Signature {
+    pub id: u32,
+    pub len: u32,
+    pub location: u32,
+    pub direction: u32,
+    pub action: u32,
+    pub pattern: [u8;SIG_PATTERN_LEN],
+    pub mask: [u8;SIG_PATTERN_LEN],
+}
+
+/** Counters (STATS , per-CPU array of u64)
+ * Updated by intrf, egtrf and bpflsm, summed over the CPUs by trf
+ * (Prometheus /metrics). Directional counters use two entries:
+ * index + etype (0 => outbound , 1 => inbound).
+ * Drops are also counted per (reason , rule id) in RULE_DROPS, see rule_drops_key.
+**/
+pub const STAT_PACKETS: u32 = 0;
+pub const STAT_DROPS: u32 = 2;
+pub const STAT_ALERTS: u32 = 4;
+pub const STAT_OVERRIDES: u32 = 6;
+pub const STAT_SIGNATURES: u32 = 8;         // user defined signature matches
+pub const STAT_INTERP: u32 = 10;            // `${` regex matches (inbound)
+pub const STAT_JNDI: u32 = 11;              // `${jndi` lookups (inbound)
+pub const STAT_INTERP_SIGS: u32 = 12;       // interpolation signature matches (inbound)
+pub const STAT_LDAP_REQUESTS: u32 = 13;     // outbound LDAP requests
+pub const STAT_JRMP: u32 = 14;              // outbound JRMP handshakes
+pub const STAT_JAVA_OBJECTS: u32 = 15;      // Java class files / serialized objects (outbound)
+pub const STAT_DNS_FLAGGED: u32 = 16;       // exfil-like, correlated or long label DNS queries
+pub const STAT_LSM_CALLS: u32 = 17;         // bpf() syscalls seen by bpflsm
+pub const STAT_LSM_DENIED: u32 = 18;
+pub const STAT_SUPPRESSED: u32 = 19;        // packets counted without an event (see VERBOSITY_*)
+pub const STAT_EVENTS_DROPPED: u32 = 21;    // ring buffer full (EVENTS)
+pub const STAT_MAX: u32 = 23;
+
+// (metric , label name , label value) of each STATS entry
+pub const STAT_METRICS: [(&str, &str, &str); STAT_MAX as usize] = [
+    ("packets", "direction", "outbound"), ("packets", "direction", "inbound"),
+    ("drops", "direction", "outbound"), ("drops", "direction", "inbound"),
+    ("alerts", "direction", "outbound"), ("alerts", "direction", "inbound"),
+    ("overrides", "direction", "outbound"), ("overrides", "direction", "inbound"),
+    ("signature_matches", "direction", "outbound"), ("signature_matches", "direction", "inbound"),
+    ("injection_matches", "kind", "interpolation"), ("injection_matches", "kind", "jndi_lookup"),
+    ("injection_matches", "kind", "interpolation_signature"), ("detections", "kind", "ldap_request"),
+    ("detections", "kind", "jrmp"), ("detections", "kind", "java_object"), ("detections", "kind", "dns"),
+    ("lsm_bpf_calls", "", ""), ("lsm_denied", "", ""),
+    ("events_suppressed", "direction", "outbound"), ("events_suppressed", "direction", "inbound"),
+    ("events_dropped", "direction", "outbound"), ("events_dropped", "direction", "inbound"),
+];
+
+// Capacity of the state hash maps (RTX, WHLIST, BLOCKLIST, INJECTIONS, RULE_DROPS)
+pub const STATE_MAP_ENTRIES: u32 = 1024;
+
+/** Event verbosity (RULE_SET idx 11 , `verbosity`)
+ * Every IPv4 packet updates STATS and FLOWS, events are only sent for:
+ *      VERBOSITY_VERDICTS   => drops, overrides, alerts and state changes (BLOCKLIST, LOOKUPS, INJECTIONS)
+ *      VERBOSITY_DETECTIONS => + detections without a verdict (LDAP, JRMP, Java objects, flagged DNS, `${`, signatures)
+ *      VERBOSITY_ALL        => every packet (debugging)
+**/
+pub const VERBOSITY_VERDICTS: u32 = 0;
+pub const VERBOSITY_DETECTIONS: u32 = 1;
+pub const VERBOSITY_ALL: u32 = 2;
+
+// Capacity of FLOWS (per-CPU LRU hash: flow id --> FlowStats), least recently seen flows are evicted
+pub const FLOW_MAP_ENTRIES: u32 = 8192;
+
+// Per-flow counters, index 0 => outbound , 1 => inbound (see flow_id)
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FlowStats {
+    pub packets: [u64;2usize],
+    pub bytes: [u64;2usize],
+    pub first_ns: u64,      // bpf_ktime_get_ns of the first / last packet (per CPU)
+    pub last_ns: u64,
+}
+
+// RULE_DROPS key: verdict reason (REASON_*) , rule id
+pub fn rule_drops_key(reason: u32, rule: u32) -> u64 {
+    ((reason as u64) << 32) | rule as u64
+}
+
+/** EventLog (v4)
+ * eversion --> EVENT_VERSION of the eBPF program that wrote the event (always the first field)
  * etype --> Type of event:
  *              0 => Outbound traffic (XDP)
  *              1 => Inbound traffic  (TC)
  *
+ * ets --> bpf_ktime_get_ns (monotonic, ns since boot)
  * eroute --> [source addres , destination address]
- * eaction --> [ctxdrop , ctxoverride]
+ * eports --> [source port , destination port] (TCP/UDP, 0 otherwise)
+ * eproto --> IP protocol
+ * eifindex --> Interface index (XDP ingress / TC skb)
+ * eflow --> Flow id, see flow_id (same id for both directions)
+ * epid --> [tgid , pid], always 0 for packet events: XDP and TC run in softirq context
+ *              (no owning task) and sched_cls only gets bpf_get_current_pid_tgid from 6.10
+ * eaction --> [ctxdrop , ctxoverride , ctxalert]
  * elvls --> Depends on etype:
- *              0 => [ TCP Traffic (y/n) , HTTP GET (1) or HTTP Resp (2) , LDAP Resp (y/n) ]
- *              1 => [ `${` regex match (y/n) , `${jndi` regex match (y/n) , `:ldap` regex match (y/n) ]
+ *              0 => [ TCP Traffic (y/n) , HTTP GET (1) or HTTP Resp (2) , LDAP Req/Resp (protocolOp) , JRMP (protocol) ,
+ *                     Java class file (1) or Java serialized object (2) , DNS query (flags) ]
+ *              1 => [ `${` regex match (y/n) , `${jndi` regex match (y/n) , `:ldap` regex match (y/n) ,
+ *                     Interpolation family , Interpolation signature (INTERP_SIGS idx + 1) , - ]
  *                                                                                      |
  *                                                                              assumed that `${jndi` was also found
+ * ereason --> [verdict reason (REASON_*) , rule id , whitelist entry (index + 1) that overrode the verdict]
+ *              reason/rule id explain the drop (or the alert if nothing was dropped)
+ * esig --> Id of the matched user defined signature (0 if none)
+ * edn --> LDAP searchRequest base DN or DNS QNAME (etype 0 only), zero padded
+ * esnip --> [snippet location (SIG_LOC_*) , snippet length], see SNIPPET_LEN
+ * esnippet --> Raw copy of the matched header value / URI fragment / signature match
+ *              (up to the end of the line), only esnip[1] bytes are valid - not escaped
+ *
+ * Ring buffer records (default) stop after the snippet size class (see event_record_len),
+ * the perf fallback (trf-ebpf `perf-events` feature) always sends the whole EventLog.
 **/
-
 #[repr(C)]
 #[derive(Clone, Copy)]
 pub struct EventLog {
+    pub eversion: u32,
     pub etype: u32,
+    pub ets: u64,
     pub eroute: [u32;2usize],
-    pub eaction: [u32;2usize],
-    pub elvls: [u32;3usize],
+    pub eports: [u32;2usize],
+    pub eproto: u32,
+    pub eifindex: u32,
+    pub eflow: u32,
+    pub epid: [u32;2usize],
+    pub eaction: [u32;3usize],
+    pub elvls: [u32;6usize],
+    pub ereason: [u32;3usize],
+    pub esig: u32,
+    pub esnip: [u32;2usize],
+    pub edn: [u8;LDAP_DN_LEN],
+    pub esnippet: [u8;SNIPPET_LEN],
 }
 // Distinct struct fields may induce padding issues (that why we use the same type - u32)
+// edn and esnippet are kept as the last fields with a length multiple of 4 for the same reason
+// ets is the only u64: it sits on an 8 byte offset and the u32 count after it is even (no padding)
+
+/** Event ABI
+ * Bump EVENT_VERSION whenever EventLog changes. The eBPF object embeds
+ * EventAbi (magic, version, size), userspace compares it with its own
+ * EventLog before loading the programs (find_event_abi) and checks the
+ * version of every event (EventLog::from_bytes).
+**/
+pub const EVENT_VERSION: u32 = 4;
+pub const EVENT_ABI_MAGIC: [u8;8usize] = *b"eLogJABI";
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct EventAbi {
+    pub magic: [u8;8usize],
+    pub version: u32,
+    pub size: u32,
+}
+
+pub const EVENT_ABI: EventAbi = EventAbi {
+    magic: EVENT_ABI_MAGIC,
+    version: EVENT_VERSION,
+    size: core::mem::size_of::<EventLog>() as u32,
+};
+
+// (version , size) of the EventAbi embedded in an eBPF object (little endian - bpfel)
+pub fn find_event_abi(obj: &[u8]) -> Option<(u32, u32)> {
+    let pos = obj.windows(EVENT_ABI_MAGIC.len()).position(|w| w == EVENT_ABI_MAGIC)?;
+    let abi = obj.get(pos + 8..pos + 16)?;
+    let version = u32::from_le_bytes([abi[0], abi[1], abi[2], abi[3]]);
+    let size = u32::from_le_bytes([abi[4], abi[5], abi[6], abi[7]]);
+    Some((version, size))
+}
+
+/** Event records (EVENTS)
+ * EventLog without esnippet (EVENT_HEADER_LEN bytes) followed by the snippet
+ * rounded up to SNIPPET_CLASS bytes: the ring buffer reservation size has to
+ * be a constant, trf-ebpf picks one per class.
+**/
+pub const EVENT_HEADER_LEN: usize = core::mem::size_of::<EventLog>() - SNIPPET_LEN;
+pub const SNIPPET_CLASS: usize = 64;
+
+// Ring buffer size (bytes), a power of 2 multiple of the page size
+pub const EVENT_RING_SIZE: u32 = 1 << 22;
+
+// Record size of an event carrying a `len` bytes snippet
+pub const fn event_record_len(len: usize) -> usize {
+    EVENT_HEADER_LEN + len.div_ceil(SNIPPET_CLASS) * SNIPPET_CLASS
+}
+
+impl EventLog {
+    // Copies a record (ring buffer or perf buffer), the missing snippet bytes are zeroed
+    pub fn from_record(buf: &[u8]) -> Option<EventLog> {
+        if buf.len() < EVENT_HEADER_LEN {
+            return None;
+        }
+        let len = buf.len().min(core::mem::size_of::<EventLog>());
+        let mut event = core::mem::MaybeUninit::<EventLog>::zeroed();
+        unsafe {
+            core::ptr::copy_nonoverlapping(buf.as_ptr(), event.as_mut_ptr() as *mut u8, len);
+            Some(event.assume_init())
+        }
+    }
+
+    // Reads an event, None if the record is too short (header or snippet) or the version differs
+    pub fn from_bytes(buf: &[u8]) -> Option<EventLog> {
+        let event = EventLog::from_record(buf)?;
+        if event.eversion != EVENT_VERSION || event.esnip[1] as usize > buf.len() - EVENT_HEADER_LEN {
+            return None;
+        }
+        Some(event)
+    }
+}
+
+/** Flow id:
+ * FNV-1a over (proto , lower endpoint , higher endpoint), endpoints are
+ * ordered so that both directions of a connection share the same id.
+**/
+pub fn flow_id(saddr: u32, daddr: u32, sport: u32, dport: u32, proto: u32) -> u32 {
+    let (a, b) = if (saddr, sport) <= (daddr, dport) {
+        ((saddr, sport), (daddr, dport))
+    } else {
+        ((daddr, dport), (saddr, sport))
+    };
+    let mut hash: u32 = 0x811c9dc5;
+    for word in [proto, a.0, a.1, b.0, b.1] {
+        for byte in word.to_le_bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+    }
+    hash
+}
 
 #[cfg(feature = "user")]
 unsafe impl aya::Pod for EventLog {}
-// Pod trait allows EventLog to be converted to/from a byte-slice
\ No newline at end of file
+// Pod trait allows EventLog to be converted to/from a byte-slice
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for Signature {}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for FlowStats {}
+
+#[cfg(feature = "user")]
+unsafe impl aya::Pod for Injection {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{host_hash, qname_hash, HOST_BYTES, LDAP_DN_LEN};
+    use crate::{dns_charset_add, dns_charset_high_entropy, DNS_ENTROPY_LABEL_LEN};
+
+    // DNS_ENTROPY of a first label, as checked by intrf
+    fn label_entropy(label: &[u8]) -> bool {
+        let mut charset = [0u64; 2];
+        for byte in label {
+            dns_charset_add(&mut charset, *byte, u64::MAX);
+        }
+        label.len() >= DNS_ENTROPY_LABEL_LEN as usize && dns_charset_high_entropy(&charset)
+    }
+
+    #[test]
+    fn dns_entropy_test() {
+        // CDN / cloud host names
+        for label in ["storagecentralus1", "d111111abcdef8", "ec2-54-123-45-67", "storageaccountwestus2prod",
+            "azurewebsitesproductionwestus2", "kubernetesclusterapiserver", "a1b2c3d4e5f60718293a4b5c6d7e8f90"] {
+            assert!(!label_entropy(label.as_bytes()), "{}", label);
+        }
+        // leaked secret key
+        assert!(label_entropy(b"wJalrXUtnFEMIK7MDENGbPxRfiCYEXAMPLEKEY"));
+    }
+
+    #[test]
+    fn name_hash_test() {
+        let mut qname = [0u8; LDAP_DN_LEN];
+        qname[..14].copy_from_slice(b"\x04Evil\x07example\x00");
+        assert_eq!(qname_hash(&qname), host_hash(b"evil.EXAMPLE:1389/a}"));
+        assert_ne!(qname_hash(&qname), host_hash(b"evil.example.com"));
+        assert_eq!(host_hash(b"/a"), 0);
+        assert_eq!(qname_hash(&[0u8; LDAP_DN_LEN]), 0);
+        assert_eq!((HOST_BYTES[b'E' as usize], HOST_BYTES[b'-' as usize]), (b'e', b'-'));
+        assert_eq!((HOST_BYTES[b':' as usize], HOST_BYTES[0]), (0, 0));
+    }
+}
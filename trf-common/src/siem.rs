@@ -0,0 +1,243 @@
+use core::fmt::Write;
+use std::{format, string::{String, ToString}, vec::Vec};
+use serde::{Serialize, Deserialize};
+use crate::event::{Event, Traffic, Verdict, ldap_op_label};
+
+/** CEF / LEEF (user feature):
+ * Event lines for ArcSight (CEF:0) and QRadar (LEEF:2.0, tab delimited).
+ * Device vendor/product/version come from the rule file (`siem:`),
+ * the event id is the verdict reason (`traffic` when there's none).
+ *
+ *  CEF:0|eLogJ|eLogJ|0.1.0|jndi_request|DROP inbound traffic|9|rt=... src=... act=DROP ...
+ *  LEEF:2.0|eLogJ|eLogJ|0.1.0|jndi_request||devTime=...<tab>src=...<tab>action=DROP ...
+**/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Device {
+    pub vendor: String,
+    pub product: String,
+    pub version: String,
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Device {
+            vendor: String::from("eLogJ"),
+            product: String::from("eLogJ"),
+            version: String::from(env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+// 0-10: pass 3, whitelist override 5, alert 7, drop 9
+fn severity(event: &Event) -> u8 {
+    match event.verdict {
+        Verdict::Drop => 9,
+        _ if event.alert => 7,
+        Verdict::Override => 5,
+        Verdict::Pass => 3,
+    }
+}
+
+fn verdict(event: &Event) -> &'static str {
+    match event.verdict {
+        Verdict::Drop => "DROP",
+        _ if event.alert => "ALERT",
+        Verdict::Override => "OVERRIDE",
+        Verdict::Pass => "PASS",
+    }
+}
+
+fn direction(event: &Event) -> &'static str {
+    match event.traffic {
+        Traffic::Inbound(_) => "inbound",
+        Traffic::Outbound(_) => "outbound",
+    }
+}
+
+fn event_id(event: &Event) -> &'static str {
+    event.reason.as_ref().map(|r| r.label).unwrap_or("traffic")
+}
+
+fn protocol(proto: u8) -> String {
+    match proto {
+        1 => String::from("ICMP"),
+        6 => String::from("TCP"),
+        17 => String::from("UDP"),
+        p => p.to_string(),
+    }
+}
+
+// Key/value pairs shared by CEF and LEEF (CEF key, LEEF key, value)
+fn attributes(event: &Event) -> Vec<(&'static str, &'static str, String)> {
+    let mut res: Vec<(&'static str, &'static str, String)> = Vec::new();
+    res.push(("src", "src", event.src.to_string()));
+    res.push(("dst", "dst", event.dst.to_string()));
+    if event.src_port != 0 || event.dst_port != 0 {
+        res.push(("spt", "srcPort", event.src_port.to_string()));
+        res.push(("dpt", "dstPort", event.dst_port.to_string()));
+    }
+    res.push(("proto", "proto", protocol(event.protocol)));
+    res.push(("act", "action", String::from(verdict(event))));
+    let (ldap, pattern, qname) = match &event.traffic {
+        Traffic::Inbound(injection) => (None, injection.pattern.clone(), None),
+        Traffic::Outbound(outbound) => (outbound.ldap.as_ref(), None, outbound.dns.as_ref().map(|d| d.qname.clone())),
+    };
+    if let Some(reason) = &event.reason {
+        res.push(("cs1", "reason", String::from(reason.label)));
+        if let Some(rule) = reason.rule {
+            res.push(("cn1", "ruleId", rule.to_string()));
+        }
+    }
+    if let Some(id) = event.signature {
+        res.push(("cn2", "signatureId", id.to_string()));
+    }
+    let detections = event.detections();
+    if !detections.is_empty() {
+        res.push(("cs2", "detections", detections.join(",")));
+    }
+    if let Some(ldap) = ldap {
+        res.push(("cs3", "ldapOp", String::from(ldap_op_label(&ldap.op))));
+        if let Some(dn) = &ldap.base_dn {
+            res.push(("cs4", "ldapBaseDn", dn.clone()));
+        }
+    }
+    if let Some(pattern) = pattern {
+        res.push(("cs5", "pattern", pattern));
+    }
+    if let Some(qname) = qname {
+        res.push(("destinationDnsDomain", "dnsQuery", qname));
+    }
+    if let Some(snippet) = &event.snippet {
+        res.push(("cs6", "snippet", snippet.text.clone()));
+    }
+    res.push(("externalId", "flowId", format!("{:08x}", event.flow_id)));
+    if let Some(pid) = event.pid {
+        res.push(("dvcpid", "pid", pid.to_string()));
+    }
+    res
+}
+
+// CEF labels of the custom fields
+const CEF_LABELS: [(&str, &str); 8] = [("cs1", "reason"), ("cn1", "ruleId"), ("cn2", "signatureId"),
+    ("cs2", "detections"), ("cs3", "ldapOp"), ("cs4", "ldapBaseDn"), ("cs5", "pattern"), ("cs6", "snippet")];
+
+// Header fields: `\` and `|`
+fn escape_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+// CEF extension values: `\`, `=` and line breaks
+fn escape_cef(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('=', "\\=").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+// LEEF values: the delimiter (tab) and line breaks are replaced
+fn escape_leef(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+// time_ms: event time (ms since epoch)
+pub fn cef(event: &Event, device: &Device, time_ms: u64) -> String {
+    let mut res = format!("CEF:0|{}|{}|{}|{}|{} {} traffic|{}|rt={} deviceDirection={}", escape_header(&device.vendor),
+        escape_header(&device.product), escape_header(&device.version), event_id(event), verdict(event),
+        direction(event), severity(event), time_ms, if direction(event) == "inbound" { 0 } else { 1 });
+    for (key, _, value) in attributes(event) {
+        let _ = write!(res, " {}={}", key, escape_cef(&value));
+        if let Some((_, label)) = CEF_LABELS.iter().find(|(k, _)| *k == key) {
+            let _ = write!(res, " {}Label={}", key, label);
+        }
+    }
+    res
+}
+
+pub fn leef(event: &Event, device: &Device, time_ms: u64) -> String {
+    let mut res = format!("LEEF:2.0|{}|{}|{}|{}||devTime={}\tdevTimeFormat=MMM dd yyyy HH:mm:ss.SSS z\tsev={}\tcat={}",
+        escape_header(&device.vendor), escape_header(&device.product), escape_header(&device.version),
+        event_id(event), utc_time(time_ms), severity(event), direction(event));
+    for (_, key, value) in attributes(event) {
+        let _ = write!(res, "\t{}={}", key, escape_leef(&value));
+    }
+    res
+}
+
+// ms since epoch --> `Oct 19 2026 08:09:10.123 UTC` (civil date from days, proleptic Gregorian)
+fn utc_time(time_ms: u64) -> String {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let secs = time_ms / 1000;
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days / 146097;
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{} {:02} {} {:02}:{:02}:{:02}.{:03} UTC", MONTHS[(month - 1) as usize], day, year,
+        secs % 86400 / 3600, secs % 3600 / 60, secs % 60, time_ms % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::string::String;
+    use crate::{EventLog, EVENT_VERSION, LDAP_DN_LEN, SNIPPET_LEN, REASON_LDAP_REQUEST, REASON_DNS, DNS_QUERY,
+        DNS_KEY_PREFIX, SIG_LOC_PAYLOAD};
+    use crate::event::Event;
+    use crate::siem::{cef, leef, utc_time, Device};
+
+    #[test]
+    fn siem_test() {
+        let mut data = EventLog {
+            eversion: EVENT_VERSION,
+            etype: 0,
+            ets: 42,
+            eroute: [0xac110002, 0x0a000001],
+            eports: [40000, 1389],
+            eproto: 6,
+            eifindex: 3,
+            eflow: 0xabcd,
+            epid: [0, 0],
+            eaction: [1, 0, 0],
+            elvls: [1, 120, 99, 0, 0, 0],
+            ereason: [REASON_LDAP_REQUEST, 3, 0],
+            esig: 0,
+            esnip: [0, 0],
+            edn: [0u8; LDAP_DN_LEN],
+            esnippet: [0u8; SNIPPET_LEN],
+        };
+        data.edn[..10].copy_from_slice(b"dc=a=b|c\\d");
+        let event = Event::try_from(data).unwrap();
+        let device = Device { vendor: String::from("ACME|Sec"), ..Device::default() };
+
+        assert_eq!(cef(&event, &device, 1792397350123), "CEF:0|ACME\\|Sec|eLogJ|0.1.0|ldap_request|DROP outbound traffic|9|\
+            rt=1792397350123 deviceDirection=1 src=172.17.0.2 dst=10.0.0.1 spt=40000 dpt=1389 proto=TCP act=DROP \
+            cs1=ldap_request cs1Label=reason cn1=3 cn1Label=ruleId cs2=tcp,ldap cs2Label=detections \
+            cs3=searchRequest cs3Label=ldapOp cs4=dc\\=a\\=b|c\\\\d cs4Label=ldapBaseDn externalId=0000abcd");
+        assert_eq!(leef(&event, &device, 1792397350123), "LEEF:2.0|ACME\\|Sec|eLogJ|0.1.0|ldap_request||\
+            devTime=Oct 19 2026 08:09:10.123 UTC\tdevTimeFormat=MMM dd yyyy HH:mm:ss.SSS z\tsev=9\tcat=outbound\t\
+            src=172.17.0.2\tdst=10.0.0.1\tsrcPort=40000\tdstPort=1389\tproto=TCP\taction=DROP\treason=ldap_request\t\
+            ruleId=3\tdetections=tcp,ldap\tldapOp=searchRequest\tldapBaseDn=dc=a=b|c\\d\tflowId=0000abcd");
+
+        // DNS query (own key) with a payload snippet (escaped, then CEF/LEEF escaped)
+        data.eports = [40000, 53];
+        data.eproto = 17;
+        data.eaction = [0, 0, 1];
+        data.elvls = [0, 0, 0, 0, 0, DNS_QUERY | DNS_KEY_PREFIX];
+        data.ereason = [REASON_DNS, 8, 0];
+        data.edn = [0u8; LDAP_DN_LEN];
+        data.edn[..14].copy_from_slice(b"\x04AKIA\x07example\x00");
+        data.esnip = [SIG_LOC_PAYLOAD, 8];
+        data.esnippet[..8].copy_from_slice(b"a=b\t\"c\"\n");
+        let event = Event::try_from(data).unwrap();
+        assert_eq!(cef(&event, &Device::default(), 1792397350123), "CEF:0|eLogJ|eLogJ|0.1.0|dns|ALERT outbound traffic|7|\
+            rt=1792397350123 deviceDirection=1 src=172.17.0.2 dst=10.0.0.1 spt=40000 dpt=53 proto=UDP act=ALERT \
+            cs1=dns cs1Label=reason cn1=8 cn1Label=ruleId cs2=dns_query,dns_key_prefix cs2Label=detections \
+            destinationDnsDomain=AKIA.example cs6=a\\=b\\\\x09\\\\\"c\\\\\"\\\\x0a cs6Label=snippet externalId=0000abcd");
+        assert_eq!(leef(&event, &Device::default(), 1792397350123), "LEEF:2.0|eLogJ|eLogJ|0.1.0|dns||\
+            devTime=Oct 19 2026 08:09:10.123 UTC\tdevTimeFormat=MMM dd yyyy HH:mm:ss.SSS z\tsev=7\tcat=outbound\t\
+            src=172.17.0.2\tdst=10.0.0.1\tsrcPort=40000\tdstPort=53\tproto=UDP\taction=ALERT\treason=dns\t\
+            ruleId=8\tdetections=dns_query,dns_key_prefix\tdnsQuery=AKIA.example\tsnippet=a=b\\x09\\\"c\\\"\\x0a\tflowId=0000abcd");
+        assert_eq!(utc_time(951782400000), "Feb 29 2000 00:00:00.000 UTC");
+    }
+}
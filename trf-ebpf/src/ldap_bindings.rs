@@ -1,3 +1,9 @@
+// LDAP (BER) tags used while walking a request
+pub const BER_SEQUENCE: u8 = 48;        // 0x30 - LDAPMessage
+pub const BER_INTEGER: u8 = 2;          // 0x02 - MessageID
+pub const BER_OCTET_STRING: u8 = 4;     // 0x04 - LDAPDN
+pub const SEARCH_REQUEST: u8 = 99;      // 0x63 - searchRequest
+
 pub struct LdapBindgs {
     protocol_req_op_pool: [u8; 3],  // (Request) Protocol Operations
     protocol_res_op_pool: [u8; 3],  // (Response) Protocol Operations
@@ -19,7 +25,19 @@ impl LdapBindgs {
         true
     }
 
+    // Verify if protocol op within (request) boundaries
+    pub fn check_protocol_req_op_type(&self, fbyte: u8) -> bool {
+        if self.protocol_req_op_pool.iter().all(|op| op != &fbyte) {
+            return false
+        }
+        true
+    }
+
     pub fn get_protocol_op_pool(&self) -> [u8; 3] {
         self.protocol_res_op_pool
     }
-}
\ No newline at end of file
+
+    pub fn get_protocol_req_op_pool(&self) -> [u8; 3] {
+        self.protocol_req_op_pool
+    }
+}
